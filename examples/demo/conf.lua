@@ -0,0 +1,11 @@
+function rock.conf(t)
+    t.identity = "demo"
+    t.debug = true
+
+    t.window.title = "Rock demo"
+    t.window.width = 1280
+    t.window.height = 720
+    t.window.resizable = true
+    t.window.vsync = false
+    t.window.msaa = 4
+end
@@ -0,0 +1,40 @@
+--State in `rock.persistent` survives hot-reloads of this file
+rock.persistent = {
+    t = 0,
+    models = {},
+}
+
+function rock.load()
+    print("test!")
+
+    --You can use this graphics function anywhere, because
+    --it merely modifies the underlying pipeline state's clear colour
+    -- rock.graphics.clear(1, 0, 0, 1)
+
+    local meshes, transforms = rock.graphics.load_mesh("sphere.glb", "glb")
+    for i=1,#meshes do
+        rock.persistent.models[i] = {meshes[i], transforms[i]}
+    end
+end
+
+function rock.reload()
+    print("reloaded!")
+end
+
+function rock.update(dt)
+    local state = rock.persistent
+    state.t = state.t + dt
+    local tmp = rock.math.quat_euler(3.14/4,0,0)
+    for i=1,#state.models do
+        state.models[i][2]:setRotation(tmp)
+        -- state.models[i][2]:setScale(rock.math.vec3(0.075, 0.075, 0.075))
+    end
+end
+
+function rock.draw()
+    local state = rock.persistent
+    -- rock.graphics.clear(math.sin(state.t), math.cos(state.t), 1, 1)
+    for i=1,#state.models do
+        rock.graphics.draw(state.models[i][1], state.models[i][2])
+    end
+end
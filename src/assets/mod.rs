@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use luminance_sdl2::GL33Surface;
+
+use crate::graphics::{self, Mesh, MeshByteFormat, ShaderProgram};
+use crate::math::Transform;
+use crate::vfs::VirtualFileSystem;
+use crate::error::Result;
+use crate::vfs::watcher::{FileWatcher, DEFAULT_POLL_INTERVAL};
+
+/// What a watched file gets reloaded as.
+#[derive(Copy, Clone, PartialEq)]
+pub enum AssetKind {
+    Mesh,
+    Shader,
+    Script,
+    /// Only watched for `rock.filechanged`
+    Other,
+}
+
+struct MeshAsset {
+    format: MeshByteFormat,
+    meshes: Vec<(Mesh, Transform)>,
+}
+
+/// Keeps track of loaded assets and the files they came from,
+/// so they can be reloaded when those files change.
+/// Meshes are cached per path, so every handle to a mesh
+/// gets updated when its file is reloaded.
+pub struct Assets {
+    watcher: FileWatcher,
+    kinds: HashMap<String, AssetKind>,
+    meshes: HashMap<String, MeshAsset>,
+    shader: Option<(String, String)>, //Vertex and fragment shader paths of the current shader
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Self {
+            watcher: FileWatcher::new(DEFAULT_POLL_INTERVAL),
+            kinds: HashMap::new(),
+            meshes: HashMap::new(),
+            shader: None,
+        }
+    }
+
+    pub fn kind(&self, path: &str) -> Option<AssetKind> {
+        self.kinds.get(path).copied()
+    }
+
+    /// Starts watching a file, without overriding what kind of asset it already is.
+    pub fn watch(&mut self, vfs: &dyn VirtualFileSystem, path: &str, kind: AssetKind) {
+        if !self.watcher.is_watched(path) {
+            self.watcher.watch(vfs, path);
+        }
+        let entry = self.kinds.entry(path.to_owned()).or_insert(kind);
+        if *entry == AssetKind::Other {
+            *entry = kind;
+        }
+    }
+
+    /// Returns all watched files that changed since the last poll.
+    pub fn poll(&mut self, vfs: &dyn VirtualFileSystem) -> Vec<String> {
+        self.watcher.poll(vfs)
+    }
+
+    /// Loads the meshes in a file, or returns the cached ones if it was loaded before.
+    /// Without a surface (when running headless), the meshes get no GPU data.
+    pub fn load_mesh(&mut self, vfs: &dyn VirtualFileSystem, surface: Option<&mut GL33Surface>, path: &str, format: MeshByteFormat) -> Result<Vec<(Mesh, Transform)>> {
+        if let Some(asset) = self.meshes.get(path) {
+            return Ok(asset.meshes.clone());
+        }
+        let mut bytes = Vec::new();
+        vfs.read_bytes(path, &mut bytes)?;
+        let meshes = graphics::meshes_from_bytes(surface, bytes, format)?;
+        self.meshes.insert(path.to_owned(), MeshAsset {
+            format: format,
+            meshes: meshes.clone(),
+        });
+        self.watch(vfs, path, AssetKind::Mesh);
+        Ok(meshes)
+    }
+
+    /// Reloads the meshes in a file, swapping the new GPU data into the existing handles.
+    pub fn reload_mesh(&mut self, vfs: &dyn VirtualFileSystem, surface: Option<&mut GL33Surface>, path: &str) -> Result<()> {
+        let asset = match self.meshes.get(path) {
+            Some(asset) => asset,
+            None => return Ok(()),
+        };
+        let mut bytes = Vec::new();
+        vfs.read_bytes(path, &mut bytes)?;
+        let new_meshes = graphics::meshes_from_bytes(surface, bytes, asset.format)?;
+        if new_meshes.len() != asset.meshes.len() {
+            warn!("`{}` now has {} meshes instead of {}, only the first {} were reloaded", path, new_meshes.len(), asset.meshes.len(), asset.meshes.len().min(new_meshes.len()));
+        }
+        for ((old, _), (new, _)) in asset.meshes.iter().zip(new_meshes.iter()) {
+            old.swap(new);
+        }
+        Ok(())
+    }
+
+    /// Compiles a shader from files, and watches them for changes.
+    pub fn load_shader(&mut self, vfs: &dyn VirtualFileSystem, surface: &mut GL33Surface, vs_path: &str, fs_path: &str) -> Result<ShaderProgram> {
+        let program = shader_from_files(vfs, surface, vs_path, fs_path)?;
+        self.watch(vfs, vs_path, AssetKind::Shader);
+        self.watch(vfs, fs_path, AssetKind::Shader);
+        self.shader = Some((vs_path.to_owned(), fs_path.to_owned()));
+        Ok(program)
+    }
+
+    /// Forgets the current shader, so it no longer gets reloaded.
+    pub fn clear_shader(&mut self) {
+        self.shader = None;
+    }
+
+    /// Recompiles the current shader, if there is one.
+    pub fn reload_shader(&self, vfs: &dyn VirtualFileSystem, surface: &mut GL33Surface) -> Option<Result<ShaderProgram>> {
+        let (vs_path, fs_path) = self.shader.as_ref()?;
+        Some(shader_from_files(vfs, surface, vs_path, fs_path))
+    }
+}
+
+fn shader_from_files(vfs: &dyn VirtualFileSystem, surface: &mut GL33Surface, vs_path: &str, fs_path: &str) -> Result<ShaderProgram> {
+    let read = |path: &str| -> Result<String> {
+        let mut bytes = Vec::new();
+        vfs.read_bytes(path, &mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    };
+    let vs = read(vs_path)?;
+    let fs = read(fs_path)?;
+    graphics::program_from_strings(surface, &vs, &fs)
+}
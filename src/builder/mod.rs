@@ -0,0 +1,304 @@
+use luminance_sdl2::GL33Surface;
+
+use crate::{Rock, DebugUi, BG_COLOR, MAIN_SCRIPT};
+use crate::lua_api;
+use crate::graphics::Graphics;
+use crate::vfs::{self, VirtualFileSystem};
+use crate::vfs::naive_vfs::NaiveVFS;
+use crate::vfs::archive_vfs::ArchiveVFS;
+use crate::vfs::mount_vfs::{MountVFS, PRIORITY_GAME, PRIORITY_SAVE};
+use crate::assets::{Assets, AssetKind};
+use crate::config::{Config, WindowConfig};
+use crate::context::EngineContext;
+use crate::input::GAMEPAD_MAPPINGS_FILE;
+use crate::replay::{self, Recorder, Replay};
+use crate::error::{RockError, Result};
+
+/// Where the game's files come from.
+enum GameSource {
+    /// A game directory or `.rock` package on disk
+    Path(String),
+    Vfs(Box<dyn VirtualFileSystem>),
+}
+
+/// Sets up a `Rock` engine, created with `Rock::builder()`.
+///
+/// ```no_run
+/// # fn main() -> rock::error::Result<()> {
+/// let mut rock = rock::Rock::builder()
+///     .game("examples/demo")
+///     .build()?;
+/// rock.run();
+/// # Ok(())
+/// # }
+/// ```
+pub struct RockBuilder {
+    game: Option<GameSource>,
+    save_vfs: Option<Box<dyn VirtualFileSystem>>,
+    window: Option<WindowConfig>,
+    script: String,
+    headless: bool,
+    record: Option<String>,
+    replay: Option<String>,
+}
+
+impl Default for RockBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RockBuilder {
+    pub fn new() -> Self {
+        Self {
+            game: None,
+            save_vfs: None,
+            window: None,
+            script: MAIN_SCRIPT.to_owned(),
+            headless: false,
+            record: None,
+            replay: None,
+        }
+    }
+
+    /// Runs the game directory or `.rock` package at `path`.
+    pub fn game(mut self, path: &str) -> Self {
+        self.game = Some(GameSource::Path(path.to_owned()));
+        self
+    }
+
+    /// Runs the game in the given file system, for example a `MemoryVFS` filled in by a tool or test.
+    pub fn vfs(mut self, vfs: Box<dyn VirtualFileSystem>) -> Self {
+        self.game = Some(GameSource::Vfs(vfs));
+        self
+    }
+
+    /// Writes to the given file system instead of the game's save directory on disk,
+    /// for example a `MemoryVFS` so tests don't leave files behind.
+    pub fn save_vfs(mut self, vfs: Box<dyn VirtualFileSystem>) -> Self {
+        self.save_vfs = Some(vfs);
+        self
+    }
+
+    /// Uses these window settings instead of the ones from `conf.lua`.
+    pub fn window(mut self, window: WindowConfig) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Runs the script at `path` (in the game's files) instead of `main.lua`.
+    pub fn script(mut self, path: &str) -> Self {
+        self.script = path.to_owned();
+        self
+    }
+
+    /// Don't open a window, drawing does nothing.
+    pub fn headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// Records every event and frame to `path` in the save directory, so the session can be replayed.
+    pub fn record(mut self, path: &str) -> Self {
+        self.record = Some(path.to_owned());
+        self
+    }
+
+    /// Plays back the recording at `path` (looked up in the save directory first) instead of taking input,
+    /// with the recorded delta times. The game gets live input again once the recording runs out.
+    pub fn replay(mut self, path: &str) -> Self {
+        self.replay = Some(path.to_owned());
+        self
+    }
+
+    /// Creates the engine and runs the game's script.
+    /// Without a game, or when the game fails to start, a message screen is shown instead.
+    /// Only fails when the engine itself can't be set up, like when the window can't be opened.
+    pub fn build(self) -> Result<Rock> {
+        //VFS initialization
+        let mut vfs = MountVFS::new();
+        let mut message_screen = match self.game {
+            Some(GameSource::Path(path)) => mount_game(&mut vfs, &path).err().map(|e| format!("Failed to load game `{}`:\n{}", path, e)),
+            Some(GameSource::Vfs(game_vfs)) => vfs.mount("/", PRIORITY_GAME, game_vfs).err().map(|e| format!("Failed to load game:\n{}", e)),
+            None => Some("No game to run!".to_owned()),
+        };
+
+        let lua = lua_api::init_lua();
+        lua_api::load_code(&lua, "print(\"hello from lua!\")").exec()?;
+
+        //Configure the game through `conf.lua`, before anything else is set up
+        let mut startup_error = None;
+        let mut config = if message_screen.is_none() {
+            Config::load(&lua, &vfs).unwrap_or_else(|e| {
+                startup_error = Some(e);
+                Config::default()
+            })
+        } else {
+            Config::default()
+        };
+        if let Some(window) = self.window {
+            config.window = window;
+        }
+        lua_api::load_modules(&lua, &config.modules);
+
+        let save_vfs: Box<dyn VirtualFileSystem> = match self.save_vfs {
+            Some(save_vfs) => save_vfs,
+            None => {
+                let save_dir = vfs::save_directory(&config.identity);
+                Box::new(NaiveVFS::from_dirs(save_dir.clone(), Some(save_dir)))
+            },
+        };
+        vfs.mount("/", PRIORITY_SAVE, save_vfs)?;
+
+        //Recordings store the seed of the game's randomness, which has to be set before the game runs
+        let replay = self.replay.and_then(|path| match Replay::load(&vfs, &path) {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                message_screen.get_or_insert_with(|| format!("Failed to load replay `{}`:\n{}", path, e));
+                None
+            },
+        });
+        let seed = replay.as_ref().map_or_else(replay::random_seed, |replay| replay.seed);
+        let recorder = self.record.and_then(|path| match Recorder::create(&vfs, &path, seed) {
+            Ok(recorder) => {
+                info!("Recording to `{}`", path);
+                Some(recorder)
+            },
+            Err(e) => {
+                warn!("Failed to start recording to `{}`: {}", path, e);
+                None
+            },
+        });
+        if replay.is_some() || recorder.is_some() {
+            lua_api::seed_random(&lua, seed)?;
+        }
+
+        let (graphics, ui) = if self.headless {
+            (None, None)
+        } else {
+            let (surface, ui) = open_window(&config.window)?;
+            let graphics = Graphics::new(surface, BG_COLOR)?;
+            (Some(graphics), Some(ui))
+        };
+        let ctx = EngineContext::new(vfs, Assets::new(), graphics).into_shared();
+        lua_api::set_context(&lua, ctx.clone())?;
+        init_gamepads(&mut ctx.borrow_mut());
+
+        //Load the game's code, which gets reloaded whenever it changes
+        if message_screen.is_none() && startup_error.is_none() {
+            let mut main_code = Vec::new();
+            let read = {
+                let mut ctx = ctx.borrow_mut();
+                let ctx = &mut *ctx;
+                let read = ctx.vfs.read_bytes(&self.script, &mut main_code);
+                //Watched even when it fails to run, so fixing it gets the game going again
+                if read.is_ok() {
+                    ctx.assets.watch(&ctx.vfs, &self.script, AssetKind::Script);
+                }
+                read
+            };
+            match read {
+                Ok(()) => {
+                    let result = lua_api::load_code_bytes(&lua, &main_code).set_name(&format!("@{}", self.script))
+                        .and_then(|chunk| chunk.exec());
+                    if let Err(e) = result {
+                        startup_error = Some(e);
+                    }
+                },
+                Err(e) => message_screen = Some(format!("Failed to read `{}`:\n{}", self.script, e)),
+            }
+        }
+
+        let mut rock = Rock {
+            lua: lua,
+            ctx: ctx,
+            ui: ui,
+            config: config,
+
+            message_screen: message_screen,
+            error_screen: None,
+            loaded: false,
+
+            accumulator: 0.0,
+            recorder: recorder,
+            replay: replay,
+        };
+        if let Some(e) = startup_error {
+            rock.show_error(e);
+        }
+        Ok(rock)
+    }
+}
+
+/// Mounts a game directory or `.rock` package at the root of the VFS.
+fn mount_game(vfs: &mut MountVFS, path: &str) -> std::io::Result<()> {
+    let p = std::path::Path::new(path);
+    let game_vfs: Box<dyn VirtualFileSystem> = if p.is_dir() {
+        Box::new(NaiveVFS::from_dirs(p.to_path_buf(), None))
+    } else if p.is_file() {
+        Box::new(ArchiveVFS::open(path)?)
+    } else {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("`{}` is not a game directory or package!", path)));
+    };
+    vfs.mount("/", PRIORITY_GAME, game_vfs)
+}
+
+/// Starts listening for gamepads, with the game's own mappings if it has them.
+/// Gamepads are optional, so failing to set them up only gets logged.
+fn init_gamepads(ctx: &mut EngineContext) {
+    let sdl = match ctx.graphics.as_ref() {
+        Some(graphics) => graphics.surface.sdl().clone(),
+        None => return,
+    };
+    if let Err(e) = ctx.input.gamepads.init(&sdl) {
+        warn!("Failed to initialize gamepads: {}", e);
+        return;
+    }
+    if ctx.vfs.exists(GAMEPAD_MAPPINGS_FILE) {
+        let mut mappings = Vec::new();
+        match ctx.vfs.read_bytes(GAMEPAD_MAPPINGS_FILE, &mut mappings) {
+            Ok(()) => info!("Loaded {} gamepad mappings", ctx.input.gamepads.add_mappings(&String::from_utf8_lossy(&mappings))),
+            Err(e) => warn!("Failed to read `{}`: {}", GAMEPAD_MAPPINGS_FILE, e),
+        }
+    }
+}
+
+/// Opens the game's window, along with the debug UI drawn in it.
+fn open_window(window_config: &WindowConfig) -> Result<(GL33Surface, DebugUi)> {
+    let surface = GL33Surface::build_with(|video| {
+        let gl_attr = video.gl_attr();
+        if window_config.msaa > 0 {
+            gl_attr.set_multisample_buffers(1);
+            gl_attr.set_multisample_samples(window_config.msaa);
+        }
+        let mut builder = video.window(&window_config.title, window_config.width, window_config.height);
+        if window_config.resizable {
+            builder.resizable();
+        }
+        if window_config.fullscreen {
+            builder.fullscreen_desktop();
+        }
+        builder
+    }).map_err(|e| RockError::Gpu(format!("Failed to open window: {:?}", e)))?;
+    unsafe { sdl2::sys::SDL_SetWindowMinimumSize(surface.window().raw(), window_config.min_width as i32, window_config.min_height as i32); }
+    let video = surface.sdl().video().map_err(|e| RockError::Gpu(format!("Failed to acquire video system: {}", e)))?;
+    let swap_interval = if window_config.vsync {
+        sdl2::video::SwapInterval::VSync
+    } else {
+        sdl2::video::SwapInterval::Immediate
+    };
+    video.gl_set_swap_interval(swap_interval).map_err(|e| RockError::Gpu(format!("Failed to set window swap interval: {}", e)))?;
+
+    //IMGUI initialization
+    let mut imgui = imgui::Context::create();
+    imgui.set_ini_filename(None);
+    let imgui_sdl2 = imgui_sdl2::ImguiSdl2::new(&mut imgui, &surface.window());
+    let renderer = imgui_opengl_renderer::Renderer::new(&mut imgui, |s| video.gl_get_proc_address(s) as _);
+
+    let ui = DebugUi {
+        imgui: imgui,
+        imgui_sdl2: imgui_sdl2,
+        renderer: renderer,
+    };
+    Ok((surface, ui))
+}
@@ -0,0 +1,206 @@
+use mlua::{Table, Result};
+
+use crate::lua_api::{self, LuaApi};
+use crate::vfs::{self, VirtualFileSystem};
+
+/// File that gets run before the window is created, to configure the game.
+pub const CONFIG_SCRIPT: &str = "conf.lua";
+
+/// Settings used to create the window.
+#[derive(Clone)]
+pub struct WindowConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub resizable: bool,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub msaa: u8, //Samples per pixel, 0 disables MSAA
+    pub min_width: u32,
+    pub min_height: u32,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: "Rock".to_owned(),
+            width: 1280,
+            height: 720,
+            resizable: false,
+            fullscreen: false,
+            vsync: false,
+            msaa: 0,
+            min_width: 1,
+            min_height: 1,
+        }
+    }
+}
+
+/// Which `rock.*` modules get loaded.
+#[derive(Clone)]
+pub struct ModulesConfig {
+    pub graphics: bool,
+    pub math: bool,
+    pub filesystem: bool,
+    pub keyboard: bool,
+    pub mouse: bool,
+    pub input: bool,
+    pub joystick: bool,
+}
+
+impl Default for ModulesConfig {
+    fn default() -> Self {
+        Self {
+            graphics: true,
+            math: true,
+            filesystem: true,
+            keyboard: true,
+            mouse: true,
+            input: true,
+            joystick: true,
+        }
+    }
+}
+
+/// How often the game logic runs.
+#[derive(Clone)]
+pub struct TimestepConfig {
+    /// Calls `rock.fixedupdate(step)` at a fixed rate, and passes the interpolation factor to `rock.draw(alpha)`
+    pub fixed: bool,
+    /// Fixed updates per second
+    pub rate: u32,
+    /// Most fixed updates in a single frame, so a slow frame can't snowball into ever slower frames
+    pub max_steps: u32,
+}
+
+impl Default for TimestepConfig {
+    fn default() -> Self {
+        Self {
+            fixed: false,
+            rate: 60,
+            max_steps: 5,
+        }
+    }
+}
+
+impl TimestepConfig {
+    /// Time between fixed updates, in seconds
+    pub fn step(&self) -> f32 {
+        1.0 / self.rate.max(1) as f32
+    }
+}
+
+/// Game configuration, filled in by `rock.conf(t)` in `conf.lua`.
+#[derive(Clone)]
+pub struct Config {
+    /// Name of the game's save directory
+    pub identity: String,
+    /// Whether the debug windows are shown
+    pub debug: bool,
+    pub window: WindowConfig,
+    pub modules: ModulesConfig,
+    pub timestep: TimestepConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            identity: "rock".to_owned(),
+            debug: true,
+            window: WindowConfig::default(),
+            modules: ModulesConfig::default(),
+            timestep: TimestepConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Runs `conf.lua` (if the game has one) and calls `rock.conf(t)`
+    /// with a table holding the default configuration.
+    pub fn load(lua: &LuaApi, vfs: &dyn VirtualFileSystem) -> Result<Self> {
+        let config = Self::default();
+        if !vfs.exists(CONFIG_SCRIPT) {
+            return Ok(config);
+        }
+
+        let mut code = Vec::new();
+        vfs.read_bytes(CONFIG_SCRIPT, &mut code).map_err(mlua::Error::external)?;
+        lua_api::load_code_bytes(lua, &code).set_name(&format!("@{}", CONFIG_SCRIPT))?.exec()?;
+
+        let table = config.to_table(lua)?;
+        lua_api::call_optional_rock_func(lua, "conf", table.clone())?;
+        Self::from_table(&table)
+    }
+
+    fn to_table<'lua>(&self, lua: &'lua LuaApi) -> Result<Table<'lua>> {
+        let window = lua.create_table()?;
+        window.set("title", self.window.title.as_str())?;
+        window.set("width", self.window.width)?;
+        window.set("height", self.window.height)?;
+        window.set("resizable", self.window.resizable)?;
+        window.set("fullscreen", self.window.fullscreen)?;
+        window.set("vsync", self.window.vsync)?;
+        window.set("msaa", self.window.msaa)?;
+        window.set("minwidth", self.window.min_width)?;
+        window.set("minheight", self.window.min_height)?;
+
+        let modules = lua.create_table()?;
+        modules.set("graphics", self.modules.graphics)?;
+        modules.set("math", self.modules.math)?;
+        modules.set("filesystem", self.modules.filesystem)?;
+        modules.set("keyboard", self.modules.keyboard)?;
+        modules.set("mouse", self.modules.mouse)?;
+        modules.set("input", self.modules.input)?;
+        modules.set("joystick", self.modules.joystick)?;
+
+        let timestep = lua.create_table()?;
+        timestep.set("fixed", self.timestep.fixed)?;
+        timestep.set("rate", self.timestep.rate)?;
+        timestep.set("maxsteps", self.timestep.max_steps)?;
+
+        let table = lua.create_table()?;
+        table.set("identity", self.identity.as_str())?;
+        table.set("debug", self.debug)?;
+        table.set("window", window)?;
+        table.set("modules", modules)?;
+        table.set("timestep", timestep)?;
+        Ok(table)
+    }
+
+    fn from_table(table: &Table) -> Result<Self> {
+        let window: Table = table.get("window")?;
+        let modules: Table = table.get("modules")?;
+        let timestep: Table = table.get("timestep")?;
+        let identity: String = table.get("identity")?;
+        vfs::check_identity(&identity)?;
+        Ok(Self {
+            identity: identity,
+            debug: table.get("debug")?,
+            window: WindowConfig {
+                title: window.get("title")?,
+                width: window.get("width")?,
+                height: window.get("height")?,
+                resizable: window.get("resizable")?,
+                fullscreen: window.get("fullscreen")?,
+                vsync: window.get("vsync")?,
+                msaa: window.get("msaa")?,
+                min_width: window.get("minwidth")?,
+                min_height: window.get("minheight")?,
+            },
+            modules: ModulesConfig {
+                graphics: modules.get("graphics")?,
+                math: modules.get("math")?,
+                filesystem: modules.get("filesystem")?,
+                keyboard: modules.get("keyboard")?,
+                mouse: modules.get("mouse")?,
+                input: modules.get("input")?,
+                joystick: modules.get("joystick")?,
+            },
+            timestep: TimestepConfig {
+                fixed: timestep.get("fixed")?,
+                rate: timestep.get("rate")?,
+                max_steps: timestep.get("maxsteps")?,
+            },
+        })
+    }
+}
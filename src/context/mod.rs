@@ -0,0 +1,43 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use luminance_sdl2::GL33Surface;
+
+use crate::vfs::mount_vfs::MountVFS;
+use crate::assets::Assets;
+use crate::graphics::Graphics;
+use crate::input::Input;
+
+/// The state of one engine instance, shared between the engine loop
+/// and the Lua bindings (which find it through `lua_api::with_context`).
+pub struct EngineContext {
+    pub vfs: MountVFS,
+    pub assets: Assets,
+    /// `None` when running headless, in which case drawing does nothing
+    pub graphics: Option<Graphics>,
+    pub input: Input,
+}
+
+/// Shared handle to an `EngineContext`.
+/// Must not be borrowed while calling into Lua, as Lua calls back into it.
+pub type Context = Rc<RefCell<EngineContext>>;
+
+impl EngineContext {
+    pub fn new(vfs: MountVFS, assets: Assets, graphics: Option<Graphics>) -> Self {
+        Self {
+            vfs: vfs,
+            assets: assets,
+            graphics: graphics,
+            input: Input::new(),
+        }
+    }
+
+    pub fn into_shared(self) -> Context {
+        Rc::new(RefCell::new(self))
+    }
+
+    /// The window's surface, if there is one.
+    pub fn surface(&mut self) -> Option<&mut GL33Surface> {
+        self.graphics.as_mut().map(|graphics| &mut graphics.surface)
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+
+/// Everything that can go wrong inside the engine.
+#[derive(Debug)]
+pub enum RockError {
+    /// Reading or writing a file through the VFS failed
+    Vfs(std::io::Error),
+    /// An asset couldn't be decoded
+    Asset(String),
+    /// A shader failed to compile or link
+    Shader(String),
+    /// Creating or using a GPU resource failed
+    Gpu(String),
+    /// Lua code failed to load or run
+    Lua(mlua::Error),
+}
+
+pub type Result<T> = std::result::Result<T, RockError>;
+
+impl fmt::Display for RockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RockError::Vfs(e) => write!(f, "VFS error: {}", e),
+            RockError::Asset(e) => write!(f, "Asset error: {}", e),
+            RockError::Shader(e) => write!(f, "Shader error: {}", e),
+            RockError::Gpu(e) => write!(f, "GPU error: {}", e),
+            RockError::Lua(e) => write!(f, "Lua error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RockError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RockError::Vfs(e) => Some(e),
+            RockError::Lua(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RockError {
+    fn from(e: std::io::Error) -> Self {
+        RockError::Vfs(e)
+    }
+}
+
+impl From<mlua::Error> for RockError {
+    fn from(e: mlua::Error) -> Self {
+        RockError::Lua(e)
+    }
+}
+
+/// Lets Rust functions called from Lua use `?` on engine errors,
+/// which Lua scripts can then catch with `pcall`.
+impl From<RockError> for mlua::Error {
+    fn from(e: RockError) -> Self {
+        match e {
+            RockError::Lua(e) => e,
+            e => mlua::Error::external(e),
+        }
+    }
+}
+
+/// Adds the source line below every `file:line:` location in a Lua error message
+/// or traceback, looking the files up through the VFS.
+pub fn annotate_traceback(message: &str, vfs: &dyn crate::vfs::VirtualFileSystem) -> String {
+    let mut sources: std::collections::HashMap<String, Option<Vec<String>>> = std::collections::HashMap::new();
+    let mut result = String::new();
+    for line in message.lines() {
+        result.push_str(line);
+        result.push('\n');
+
+        //Locations look like `main.lua:12: ...`, chunks without a file look like `[string "..."]:12:`
+        let mut parts = line.trim_start().splitn(3, ':');
+        let (path, line_nr) = match (parts.next(), parts.next().and_then(|n| n.parse::<usize>().ok())) {
+            (Some(path), Some(line_nr)) if !path.starts_with('[') && line_nr > 0 => (path, line_nr),
+            _ => continue,
+        };
+        let source = sources.entry(path.to_owned()).or_insert_with(|| {
+            let mut bytes = Vec::new();
+            vfs.read_bytes(path, &mut bytes).ok()?;
+            Some(String::from_utf8_lossy(&bytes).lines().map(|l| l.to_owned()).collect())
+        });
+        if let Some(code) = source.as_ref().and_then(|lines| lines.get(line_nr - 1)) {
+            result.push_str(&format!("        > {}\n", code.trim()));
+        }
+    }
+    result
+}
@@ -0,0 +1,167 @@
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
+
+use serde::{Serialize, Deserialize};
+
+use crate::lua_api::{self, LuaApi};
+
+/// Input and window events the game gets told about,
+/// each passed to a Love2D-style callback in the `rock` table.
+/// Serializable so they can be recorded and replayed (see `replay`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RockEvent {
+    /// `rock.keypressed(key, scancode, isrepeat)`
+    KeyPressed { key: String, scancode: String, repeat: bool },
+    /// `rock.keyreleased(key, scancode)`
+    KeyReleased { key: String, scancode: String },
+    /// `rock.textinput(text)`
+    TextInput { text: String },
+    /// `rock.mousepressed(x, y, button, istouch, presses)`
+    MousePressed { x: i32, y: i32, button: u8, presses: u8 },
+    /// `rock.mousereleased(x, y, button, istouch, presses)`
+    MouseReleased { x: i32, y: i32, button: u8, presses: u8 },
+    /// `rock.mousemoved(x, y, dx, dy, istouch)`
+    MouseMoved { x: i32, y: i32, dx: i32, dy: i32 },
+    /// `rock.wheelmoved(x, y)`
+    WheelMoved { x: i32, y: i32 },
+    /// `rock.resize(w, h)`
+    Resize { width: i32, height: i32 },
+    /// `rock.focus(focused)`
+    Focus(bool),
+    /// `rock.visible(visible)`
+    Visible(bool),
+    /// `rock.filedropped(path)`, with the path on the user's disk
+    FileDropped { path: String },
+    /// `rock.joystickadded(id)`, the name can be looked up with `rock.joystick.getName(id)`
+    JoystickAdded { id: u32, name: String },
+    /// `rock.joystickremoved(id)`
+    JoystickRemoved { id: u32 },
+    /// `rock.gamepadpressed(id, button)`
+    GamepadPressed { id: u32, button: String },
+    /// `rock.gamepadreleased(id, button)`
+    GamepadReleased { id: u32, button: String },
+    /// `rock.gamepadaxis(id, axis, value)`, with the value between -1 and 1
+    GamepadAxis { id: u32, axis: String, value: f32 },
+    /// `rock.quit()`, returning `true` cancels quitting
+    Quit,
+}
+
+impl RockEvent {
+    /// Converts an SDL event, or returns `None` if the game doesn't get told about it.
+    /// Gamepads getting connected are handled by `input::Gamepads`, as they need to be opened first.
+    pub fn from_sdl(event: &Event) -> Option<Self> {
+        let event = match event {
+            Event::KeyDown { keycode, scancode, repeat, .. } => RockEvent::KeyPressed {
+                key: keycode.map_or_else(|| "unknown".to_owned(), key_name),
+                scancode: scancode.map_or_else(|| "unknown".to_owned(), scancode_name),
+                repeat: *repeat,
+            },
+            Event::KeyUp { keycode, scancode, .. } => RockEvent::KeyReleased {
+                key: keycode.map_or_else(|| "unknown".to_owned(), key_name),
+                scancode: scancode.map_or_else(|| "unknown".to_owned(), scancode_name),
+            },
+            Event::TextInput { text, .. } => RockEvent::TextInput { text: text.clone() },
+            Event::MouseButtonDown { mouse_btn, clicks, x, y, .. } => RockEvent::MousePressed {
+                x: *x,
+                y: *y,
+                button: mouse_button_index(*mouse_btn)?,
+                presses: *clicks,
+            },
+            Event::MouseButtonUp { mouse_btn, clicks, x, y, .. } => RockEvent::MouseReleased {
+                x: *x,
+                y: *y,
+                button: mouse_button_index(*mouse_btn)?,
+                presses: *clicks,
+            },
+            Event::MouseMotion { x, y, xrel, yrel, .. } => RockEvent::MouseMoved { x: *x, y: *y, dx: *xrel, dy: *yrel },
+            Event::MouseWheel { x, y, direction, .. } => {
+                //Some platforms report "natural" scrolling flipped
+                let sign = if *direction == MouseWheelDirection::Flipped { -1 } else { 1 };
+                RockEvent::WheelMoved { x: x * sign, y: y * sign }
+            },
+            Event::Window { win_event, .. } => match win_event {
+                WindowEvent::Resized(width, height) => RockEvent::Resize { width: *width, height: *height },
+                WindowEvent::FocusGained => RockEvent::Focus(true),
+                WindowEvent::FocusLost => RockEvent::Focus(false),
+                WindowEvent::Shown | WindowEvent::Restored => RockEvent::Visible(true),
+                WindowEvent::Hidden | WindowEvent::Minimized => RockEvent::Visible(false),
+                _ => return None,
+            },
+            Event::DropFile { filename, .. } => RockEvent::FileDropped { path: filename.clone() },
+            Event::ControllerDeviceRemoved { which, .. } => RockEvent::JoystickRemoved { id: *which },
+            Event::ControllerButtonDown { which, button, .. } => RockEvent::GamepadPressed { id: *which, button: button.string() },
+            Event::ControllerButtonUp { which, button, .. } => RockEvent::GamepadReleased { id: *which, button: button.string() },
+            Event::ControllerAxisMotion { which, axis, value, .. } => RockEvent::GamepadAxis {
+                id: *which,
+                axis: axis.string(),
+                value: (*value as f32 / i16::MAX as f32).max(-1.0),
+            },
+            Event::Quit { .. } => RockEvent::Quit,
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    /// Calls the game's callback for this event, if it has one.
+    /// Returns whether the engine should quit, which only `Quit` events do (unless the game cancels it).
+    pub fn dispatch(&self, lua: &LuaApi) -> mlua::Result<bool> {
+        match self {
+            RockEvent::KeyPressed { key, scancode, repeat } => lua_api::call_optional_rock_func(lua, "keypressed", (key.as_str(), scancode.as_str(), *repeat))?,
+            RockEvent::KeyReleased { key, scancode } => lua_api::call_optional_rock_func(lua, "keyreleased", (key.as_str(), scancode.as_str()))?,
+            RockEvent::TextInput { text } => lua_api::call_optional_rock_func(lua, "textinput", text.as_str())?,
+            RockEvent::MousePressed { x, y, button, presses } => lua_api::call_optional_rock_func(lua, "mousepressed", (*x, *y, *button, false, *presses))?,
+            RockEvent::MouseReleased { x, y, button, presses } => lua_api::call_optional_rock_func(lua, "mousereleased", (*x, *y, *button, false, *presses))?,
+            RockEvent::MouseMoved { x, y, dx, dy } => lua_api::call_optional_rock_func(lua, "mousemoved", (*x, *y, *dx, *dy, false))?,
+            RockEvent::WheelMoved { x, y } => lua_api::call_optional_rock_func(lua, "wheelmoved", (*x, *y))?,
+            RockEvent::Resize { width, height } => lua_api::call_optional_rock_func(lua, "resize", (*width, *height))?,
+            RockEvent::Focus(focused) => lua_api::call_optional_rock_func(lua, "focus", *focused)?,
+            RockEvent::Visible(visible) => lua_api::call_optional_rock_func(lua, "visible", *visible)?,
+            RockEvent::FileDropped { path } => lua_api::call_optional_rock_func(lua, "filedropped", path.as_str())?,
+            RockEvent::JoystickAdded { id, .. } => lua_api::call_optional_rock_func(lua, "joystickadded", *id)?,
+            RockEvent::JoystickRemoved { id } => lua_api::call_optional_rock_func(lua, "joystickremoved", *id)?,
+            RockEvent::GamepadPressed { id, button } => lua_api::call_optional_rock_func(lua, "gamepadpressed", (*id, button.as_str()))?,
+            RockEvent::GamepadReleased { id, button } => lua_api::call_optional_rock_func(lua, "gamepadreleased", (*id, button.as_str()))?,
+            RockEvent::GamepadAxis { id, axis, value } => lua_api::call_optional_rock_func(lua, "gamepadaxis", (*id, axis.as_str(), *value))?,
+            RockEvent::Quit => {
+                let cancel: Option<bool> = lua_api::call_optional_rock_func_returning(lua, "quit", ())?;
+                return Ok(!cancel.unwrap_or(false));
+            },
+        }
+        Ok(false)
+    }
+}
+
+/// Name of a key as seen by Lua, like `"a"`, `"space"` or `"left shift"`.
+pub fn key_name(key: Keycode) -> String {
+    key.name().to_lowercase()
+}
+
+/// Name of a scancode (a key's physical location) as seen by Lua.
+pub fn scancode_name(scancode: Scancode) -> String {
+    scancode.name().to_lowercase()
+}
+
+/// Mouse buttons are numbered like in Love2D: 1 is left, 2 is right, 3 is middle.
+pub fn mouse_button_index(button: MouseButton) -> Option<u8> {
+    match button {
+        MouseButton::Left => Some(1),
+        MouseButton::Right => Some(2),
+        MouseButton::Middle => Some(3),
+        MouseButton::X1 => Some(4),
+        MouseButton::X2 => Some(5),
+        MouseButton::Unknown => None,
+    }
+}
+
+/// Inverse of `mouse_button_index`.
+pub fn mouse_button_from_index(index: u8) -> Option<MouseButton> {
+    match index {
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Right),
+        3 => Some(MouseButton::Middle),
+        4 => Some(MouseButton::X1),
+        5 => Some(MouseButton::X2),
+        _ => None,
+    }
+}
@@ -34,14 +34,8 @@ impl Camera {
         }
     }
 
-    pub fn get_proj(&self) -> Mat4 {
-        let (width, height) = {
-            let surface = unsafe { &crate::ROCK.as_ref().unwrap().surface };
-            let size = surface.window().size();
-            (size.0 as f32, size.1 as f32)
-        };
-        let aspect_ratio = width / height;
-
+    /// Projection matrix for a viewport with the given width / height ratio
+    pub fn get_proj(&self, aspect_ratio: f32) -> Mat4 {
         match self.mode {
             CameraMode::Perspective => Mat4::perspective_rh_gl(
                 self.fov,
@@ -10,6 +10,7 @@ use super::{
 
     mesh::Mesh,
 };
+use crate::error::Result;
 
 pub const TRIANGLE: [VertexType; 3] = [
     VertexType::new(
@@ -35,7 +36,7 @@ pub const TRIANGLE: [VertexType; 3] = [
     ),
 ];
 
-pub fn create_triangle(surface: &mut luminance_sdl2::GL33Surface) -> Mesh {
+pub fn create_triangle(surface: &mut luminance_sdl2::GL33Surface) -> Result<Mesh> {
     Mesh::new(surface, |builder| {
         builder.set_vertices(&TRIANGLE[..])
                 .set_indices(Vec::<u32>::new())
@@ -1,40 +1,87 @@
 use std::rc::Rc;
+use std::cell::{Ref, RefCell};
 
 use luminance_sdl2::GL33Surface;
 use luminance_gl::GL33;
 
 use luminance::context::GraphicsContext as _;
-use luminance::tess::{Tess, TessBuilder};
+use luminance::tess::{Tess, TessBuilder, Mode};
 
 use super::VertexType;
+use crate::error::{RockError, Result};
 
+enum MeshData {
+    Gpu(Tess<GL33, VertexType, u32>),
+    /// Meshes created without graphics (when running headless) only remember their size
+    Headless { vert_count: usize },
+}
+
+/// Handle to a mesh on the GPU. Clones share the same GPU data,
+/// which can be swapped out from under all of them with `swap`.
 #[derive(Clone)]
 pub struct Mesh {
-    tess: Rc<Tess<GL33, VertexType, u32>>
+    data: Rc<RefCell<MeshData>>
 }
 
 impl Mesh {
-    pub fn new<F>(surface: &mut luminance_sdl2::GL33Surface, func: F) -> Self
+    pub fn new<F>(surface: &mut luminance_sdl2::GL33Surface, func: F) -> Result<Self>
     where
         F: Fn(TessBuilder<GL33, ()>) -> TessBuilder<GL33, VertexType, u32>
     {
         let builder = func(TessBuilder::<GL33, ()>::new(surface));
-        let mesh = builder.build().expect("Failed to create mesh!"); //TODO: Error handling
+        let mesh = builder.build().map_err(|e| RockError::Gpu(format!("Failed to create mesh: {}", e)))?;
 
-        Self {
-            tess: Rc::new(mesh)
+        Ok(Self {
+            data: Rc::new(RefCell::new(MeshData::Gpu(mesh)))
+        })
+    }
+
+    /// Creates a triangle mesh, or a mesh without GPU data when there's no surface.
+    pub fn from_data(surface: Option<&mut GL33Surface>, vertices: &[VertexType], indices: &[u32]) -> Result<Self> {
+        match surface {
+            Some(surface) => Self::new(surface, |builder| {
+                builder.set_vertices(vertices)
+                       .set_indices(indices)
+                       .set_mode(Mode::Triangle)
+            }),
+            None => {
+                let vert_count = if indices.is_empty() { vertices.len() } else { indices.len() };
+                Ok(Self {
+                    data: Rc::new(RefCell::new(MeshData::Headless { vert_count: vert_count }))
+                })
+            },
         }
     }
 
-    pub fn tess(&self) -> &Tess<GL33, VertexType, u32> {
-        &self.tess
+    /// The mesh's GPU data, or `None` for meshes created without graphics.
+    pub fn tess(&self) -> Option<Ref<Tess<GL33, VertexType, u32>>> {
+        let data = self.data.borrow();
+        match *data {
+            MeshData::Gpu(_) => Some(Ref::map(data, |data| match data {
+                MeshData::Gpu(tess) => tess,
+                MeshData::Headless { .. } => unreachable!(),
+            })),
+            MeshData::Headless { .. } => None,
+        }
+    }
+
+    /// Swaps the GPU data of this mesh (and all its clones) with `other`.
+    /// Used to hot-reload meshes without invalidating existing handles.
+    pub fn swap(&self, other: &Mesh) {
+        if Rc::ptr_eq(&self.data, &other.data) {
+            return;
+        }
+        std::mem::swap(&mut *self.data.borrow_mut(), &mut *other.data.borrow_mut());
     }
 
     pub fn vert_count(&self) -> usize {
-        self.tess.vert_nb()
+        match &*self.data.borrow() {
+            MeshData::Gpu(tess) => tess.vert_nb(),
+            MeshData::Headless { vert_count } => *vert_count,
+        }
     }
 
     pub fn tri_count(&self) -> usize {
-        self.tess.vert_nb() / 3
+        self.vert_count() / 3
     }
 }
@@ -7,16 +7,19 @@ pub use mesh::Mesh;
 mod camera;
 pub use camera::{Camera, CameraMode};
 
+mod state;
+pub use state::Graphics;
+
 use luminance_sdl2::GL33Surface;
 use luminance_gl::GL33;
 
 use luminance::{Semantics, Vertex, UniformInterface};
 use luminance::context::GraphicsContext;
 use luminance::shader::{Program, Uniform};
-use luminance::tess::Mode;
 
 use glam::{Vec3, Quat};
 use crate::Transform;
+use crate::error::{RockError, Result};
 
 #[derive(Copy, Clone, Debug, PartialEq, Semantics)]
 pub enum VertexSemantics {
@@ -60,16 +63,22 @@ const VS_STR: &str = include_str!("vs2d.glsl");
 const FS_STR: &str = include_str!("fs2d.glsl");
 
 pub type ShaderProgram = Program<GL33, VertexSemantics, (), ShaderInterface>;
-pub fn get_default_program(surface: &mut GL33Surface) -> ShaderProgram {
+pub fn get_default_program(surface: &mut GL33Surface) -> Result<ShaderProgram> {
+    program_from_strings(surface, VS_STR, FS_STR)
+}
+
+/// Compiles a shader program from vertex and fragment shader sources.
+pub fn program_from_strings(surface: &mut GL33Surface, vs: &str, fs: &str) -> Result<ShaderProgram> {
     surface.new_shader_program::<VertexSemantics, (), ShaderInterface>()
-           .from_strings(VS_STR, None, None, FS_STR)
-           .expect("Failed to compile shaders!")
-           .ignore_warnings()
+           .from_strings(vs, None, None, fs)
+           .map(|built| built.ignore_warnings())
+           .map_err(|e| RockError::Shader(e.to_string()))
 }
 
 //TODO: Separate GLB and GLTF because there might be differences
 //      between the two when it comes to loading.
 #[non_exhaustive]
+#[derive(Copy, Clone)]
 pub enum MeshByteFormat {
     GLB,
     GLTF,
@@ -87,24 +96,26 @@ impl MeshByteFormat {
     }
 }
 
-pub fn meshes_from_bytes(bytes: Vec<u8>, format: MeshByteFormat) -> Vec<(Mesh, Transform)> {
+/// Loads the meshes in a file. Without a surface, the meshes get no GPU data.
+pub fn meshes_from_bytes(surface: Option<&mut GL33Surface>, bytes: Vec<u8>, format: MeshByteFormat) -> Result<Vec<(Mesh, Transform)>> {
     match format {
-        GLB => gltf_meshes_from_bytes(bytes),
-        _ => unimplemented!(),
+        MeshByteFormat::GLB | MeshByteFormat::GLTF => gltf_meshes_from_bytes(surface, bytes),
+        _ => Err(RockError::Asset("Unsupported mesh format!".to_owned())),
     }
 }
 
 /// Incredibly ugly gltf mesh import
-fn gltf_meshes_from_bytes(bytes: Vec<u8>) -> Vec<(Mesh, Transform)> {
-    let (document, buffers, images) = gltf::import_slice(bytes.as_slice()).expect("Failed to import bytes as glTF 2.0 data!");
+fn gltf_meshes_from_bytes(mut surface: Option<&mut GL33Surface>, bytes: Vec<u8>) -> Result<Vec<(Mesh, Transform)>> {
+    let (document, buffers, images) = gltf::import_slice(bytes.as_slice())
+        .map_err(|e| RockError::Asset(format!("Failed to import bytes as glTF 2.0 data: {}", e)))?;
+    let missing = |what: &str| RockError::Asset(format!("No {} data found!", what));
     let mut result = Vec::new();
-    let surface = unsafe { &mut crate::ROCK.as_mut().unwrap().surface };
     for mesh in document.meshes() {
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
         for primitive in mesh.primitives() {
             let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
-            let pos_vec: Vec<[f32; 3]> = reader.read_positions().expect("No positional data found!").collect();
+            let pos_vec: Vec<[f32; 3]> = reader.read_positions().ok_or_else(|| missing("positional"))?.collect();
             let rgb_vec: Option<Vec<[f32; 3]>> = match reader.read_colors(0) {
                 Some(data) => Some(data.into_rgb_f32().collect()),
                 None => None,
@@ -113,9 +124,13 @@ fn gltf_meshes_from_bytes(bytes: Vec<u8>) -> Vec<(Mesh, Transform)> {
                 Some(data) => Some(data.into_f32().collect()),
                 None => None,
             };
-            let normal_vec: Vec<[f32; 3]> = reader.read_normals().expect("No normal data found!").collect();
-            let tangent_vec: Vec<[f32; 4]> = reader.read_tangents().expect("No tangent data found!").collect();
-            //TODO: Check array bounds
+            let normal_vec: Vec<[f32; 3]> = reader.read_normals().ok_or_else(|| missing("normal"))?.collect();
+            let tangent_vec: Vec<[f32; 4]> = reader.read_tangents().ok_or_else(|| missing("tangent"))?.collect();
+            let too_short = |len: Option<usize>| len.map_or(false, |len| len < pos_vec.len());
+            if too_short(Some(normal_vec.len())) || too_short(Some(tangent_vec.len()))
+                || too_short(rgb_vec.as_ref().map(|v| v.len())) || too_short(uv_vec.as_ref().map(|v| v.len())) {
+                return Err(RockError::Asset("Vertex attributes don't match the amount of positions!".to_owned()));
+            }
             for i in 0..pos_vec.len() {
                 let pos = pos_vec[i];
                 let rgb = match rgb_vec {
@@ -138,20 +153,16 @@ fn gltf_meshes_from_bytes(bytes: Vec<u8>) -> Vec<(Mesh, Transform)> {
                     )
                 );
             }
-            for index in reader.read_indices().expect("No index data found!").into_u32() {
+            for index in reader.read_indices().ok_or_else(|| missing("index"))?.into_u32() {
                 indices.push(index);
             }
         }
-        let mesh = Mesh::new(surface, |builder| {
-            builder.set_vertices(vertices.clone())
-                   .set_indices(indices.clone())
-                   .set_mode(Mode::Triangle)
-        });
+        let mesh = Mesh::from_data(surface.as_deref_mut(), &vertices, &indices)?;
         let pos = Vec3::new(0.0, 0.0, 0.0);
         let rot = Quat::identity();
         let scale = Vec3::new(1.0, 1.0, 1.0); //0.075, 0.075, 0.075
         let transform = Transform::new(pos, rot, scale);
         result.push((mesh, transform));
     }
-    result
+    Ok(result)
 }
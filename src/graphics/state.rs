@@ -0,0 +1,95 @@
+use luminance_sdl2::GL33Surface;
+
+use sdl2::EventPump;
+use sdl2::mouse::Cursor;
+
+use luminance::context::GraphicsContext as _;
+use luminance::pipeline::PipelineState;
+
+use glam::*;
+
+use super::{ShaderProgram, Camera, CameraMode, get_default_program};
+use crate::math::Transform;
+use crate::error::{RockError, Result};
+
+/// The window and everything needed to draw in it: its surface and the current render state.
+pub struct Graphics {
+    pub surface: GL33Surface,
+    pub pipeline_state: PipelineState,
+    /// Window and input events, also used to query the keyboard and mouse
+    pub event_pump: EventPump,
+    /// Kept alive for as long as it's the mouse cursor
+    pub cursor: Option<Cursor>,
+
+    pub default_program: ShaderProgram,
+    pub cur_program: ShaderProgram,
+    pub camera: Camera,
+
+    //Performance variables
+    pub tri_count: usize, //Triangles rendered per frame
+    pub draw_calls: usize, //Drawcalls per frame
+}
+
+impl Graphics {
+    pub fn new(mut surface: GL33Surface, clear_color: [f32; 4]) -> Result<Self> {
+        let event_pump = surface.sdl().event_pump().map_err(|e| RockError::Gpu(format!("Failed to create event pump: {}", e)))?;
+
+        //Default shader program. 2nd program is because program doesn't implement `Clone`
+        let program = get_default_program(&mut surface)?;
+        let program2 = get_default_program(&mut surface)?;
+
+        let cam_pos = Vec3::new(0.0,0.0,-2.0);
+        let cam_rot = Quat::from_rotation_ypr(0.0, 0.0, 0.0);
+        let cam_scale = Vec3::new(1.0, 1.0, 1.0); //Useless but needed
+        let cam_transform = Transform::new(cam_pos, cam_rot, cam_scale);
+
+        let camera = Camera::new(CameraMode::Perspective, cam_transform, 60.0 / 180.0 * 3.14);
+
+        Ok(Self {
+            surface: surface,
+            pipeline_state: PipelineState::default().set_clear_color(clear_color),
+            event_pump: event_pump,
+            cursor: None,
+
+            default_program: program,
+            cur_program: program2,
+            camera: camera,
+
+            tri_count: 0,
+            draw_calls: 0,
+        })
+    }
+
+    /// Clears the screen for a new frame, and resets the frame's statistics.
+    pub fn clear(&mut self) -> Result<()> {
+        let back_buffer = self.surface.back_buffer().map_err(|e| RockError::Gpu(format!("Failed to get backbuffer: {}", e)))?;
+        let render = self.surface.new_pipeline_gate().pipeline(
+            &back_buffer,
+            &self.pipeline_state,
+            |_, _| Ok(()),
+        ).assume();
+        if !render.is_ok() {
+            return Err(RockError::Gpu("Renderer ran into unknown error!".to_owned()));
+        }
+
+        self.tri_count = 0;
+        self.draw_calls = 0;
+        Ok(())
+    }
+
+    pub fn set_clear_color(&mut self, color: [f32; 4]) {
+        self.pipeline_state = self.pipeline_state.clone().set_clear_color(color);
+    }
+
+    pub fn get_render_state(&self) -> PipelineState {
+        self.pipeline_state.clone()
+            .enable_clear_color(false)
+            .enable_clear_depth(false)
+    }
+
+    /// Width divided by height of the window
+    pub fn aspect_ratio(&self) -> f32 {
+        let (width, height) = self.surface.window().size();
+        width as f32 / height.max(1) as f32
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Serialize, Deserialize};
+use sdl2::keyboard::Keycode;
+
+use super::QUIT_ACTION;
+use crate::events::key_name;
+
+/// A single input that can drive an action, or one side of an axis.
+/// Written as `key:<name>`, `mouse:<button>`, `button:<name>` for gamepad buttons,
+/// or `axis:<name>+` / `axis:<name>-` for one direction of a gamepad axis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(String),
+    MouseButton(u8),
+    GamepadButton(String),
+    GamepadAxis { axis: String, positive: bool },
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key:{}", key),
+            Binding::MouseButton(button) => write!(f, "mouse:{}", button),
+            Binding::GamepadButton(button) => write!(f, "button:{}", button),
+            Binding::GamepadAxis { axis, positive } => write!(f, "axis:{}{}", axis, if *positive { '+' } else { '-' }),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid binding `{}`!", s);
+        let mut parts = s.splitn(2, ':');
+        let kind = parts.next().ok_or_else(invalid)?;
+        let name = parts.next().filter(|name| !name.is_empty()).ok_or_else(invalid)?.to_lowercase();
+        Ok(match kind {
+            //Stored the way the key shows up in events, so a typo doesn't leave the binding dead
+            "key" => Binding::Key(Keycode::from_name(&name).map(key_name).ok_or_else(invalid)?),
+            "mouse" => Binding::MouseButton(name.parse().map_err(|_| invalid())?),
+            "button" => Binding::GamepadButton(name),
+            "axis" => {
+                let positive = match name.chars().last() {
+                    Some('+') => true,
+                    Some('-') => false,
+                    _ => return Err(invalid()),
+                };
+                Binding::GamepadAxis { axis: name[..name.len() - 1].to_owned(), positive: positive }
+            },
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+/// Inputs pulling an axis towards -1 and towards 1.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AxisBindings {
+    pub negative: Vec<Binding>,
+    pub positive: Vec<Binding>,
+}
+
+/// A game's named actions and axes, and the inputs bound to them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bindings {
+    pub actions: HashMap<String, Vec<Binding>>,
+    pub axes: HashMap<String, AxisBindings>,
+}
+
+impl Default for Bindings {
+    /// Only the engine's own `quit` action, bound to escape.
+    fn default() -> Self {
+        let mut actions = HashMap::new();
+        actions.insert(QUIT_ACTION.to_owned(), vec![Binding::Key("escape".to_owned())]);
+        Self {
+            actions: actions,
+            axes: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bindings() {
+        assert_eq!("key:space".parse(), Ok(Binding::Key("space".to_owned())));
+        assert_eq!("key:Left Shift".parse(), Ok(Binding::Key("left shift".to_owned())));
+        assert_eq!("mouse:2".parse(), Ok(Binding::MouseButton(2)));
+        assert_eq!("button:A".parse(), Ok(Binding::GamepadButton("a".to_owned())));
+        assert_eq!("axis:leftx-".parse(), Ok(Binding::GamepadAxis { axis: "leftx".to_owned(), positive: false }));
+        assert_eq!("axis:triggerright+".parse(), Ok(Binding::GamepadAxis { axis: "triggerright".to_owned(), positive: true }));
+    }
+
+    #[test]
+    fn rejects_invalid_bindings() {
+        for binding in &["", "key", "key:", "key:spcae", "mouse:left", "axis:leftx", "joystick:a"] {
+            assert!(binding.parse::<Binding>().is_err(), "`{}` should be invalid", binding);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let bindings = vec![
+            Binding::Key("escape".to_owned()),
+            Binding::Key("left shift".to_owned()),
+            Binding::MouseButton(3),
+            Binding::GamepadButton("start".to_owned()),
+            Binding::GamepadAxis { axis: "lefty".to_owned(), positive: true },
+            Binding::GamepadAxis { axis: "lefty".to_owned(), positive: false },
+        ];
+        for binding in bindings {
+            assert_eq!(binding.to_string().parse(), Ok(binding));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use sdl2::Sdl;
+use sdl2::GameControllerSubsystem;
+use sdl2::controller::GameController;
+
+use crate::events::RockEvent;
+
+/// The SDL side of gamepads: opening them, their mappings and vibration.
+/// There's no SDL when running headless, so gamepads then only exist through (synthetic) events.
+pub struct Gamepads {
+    subsystem: Option<GameControllerSubsystem>,
+    controllers: HashMap<u32, GameController>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self {
+            subsystem: None,
+            controllers: HashMap::new(),
+        }
+    }
+
+    /// Starts listening for gamepads. Ones that are already connected get added on the next poll.
+    pub fn init(&mut self, sdl: &Sdl) -> Result<(), String> {
+        self.subsystem = Some(sdl.game_controller()?);
+        Ok(())
+    }
+
+    /// Opens a newly connected gamepad, returning the event telling the game about it.
+    pub fn open(&mut self, device_index: u32) -> Option<RockEvent> {
+        let subsystem = self.subsystem.as_ref()?;
+        match subsystem.open(device_index) {
+            Ok(controller) => {
+                let id = controller.instance_id();
+                let name = controller.name();
+                info!("Gamepad {} connected: {}", id, name);
+                self.controllers.insert(id, controller);
+                Some(RockEvent::JoystickAdded { id: id, name: name })
+            },
+            Err(e) => {
+                warn!("Failed to open gamepad {}: {}", device_index, e);
+                None
+            },
+        }
+    }
+
+    pub fn close(&mut self, id: u32) {
+        if self.controllers.remove(&id).is_some() {
+            info!("Gamepad {} disconnected", id);
+        }
+    }
+
+    /// Adds mappings in the SDL GameController database format (like `gamecontrollerdb.txt`).
+    /// Returns how many mappings were added or updated, which is always 0 when headless.
+    pub fn add_mappings(&self, mappings: &str) -> usize {
+        let subsystem = match self.subsystem.as_ref() {
+            Some(subsystem) => subsystem,
+            None => return 0,
+        };
+        let mut count = 0;
+        for line in mappings.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            match subsystem.add_mapping(line) {
+                Ok(_) => count += 1,
+                Err(e) => warn!("Skipping invalid gamepad mapping `{}`: {}", line, e),
+            }
+        }
+        count
+    }
+
+    /// Vibrates a gamepad, with strengths between 0 and 1 for its low and high frequency motors.
+    /// Returns `false` when the gamepad doesn't exist or can't vibrate.
+    pub fn set_vibration(&mut self, id: u32, low: f32, high: f32, duration_ms: u32) -> bool {
+        let strength = |value: f32| (value.max(0.0).min(1.0) * u16::MAX as f32) as u16;
+        match self.controllers.get_mut(&id) {
+            Some(controller) => controller.set_rumble(strength(low), strength(high), duration_ms).is_ok(),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+mod bindings;
+pub use bindings::{Binding, AxisBindings, Bindings};
+
+mod gamepad;
+pub use gamepad::Gamepads;
+
+use crate::events::RockEvent;
+use crate::vfs::{self, VirtualFileSystem};
+use crate::error::Result;
+
+/// Action the engine quits on (through `rock.quit`), bound to escape by default.
+pub const QUIT_ACTION: &str = "quit";
+/// File in the save directory that `rock.input.saveBindings()` writes to.
+pub const BINDINGS_FILE: &str = "bindings.bin";
+/// Gamepad mappings that get loaded at startup, if the game has them.
+pub const GAMEPAD_MAPPINGS_FILE: &str = "gamecontrollerdb.txt";
+/// Gamepad axis values closer to 0 than this count as 0, as sticks rarely rest at exactly 0.
+pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+/// Keeps track of held inputs, and the actions and axes bound to them.
+/// The state comes from the events the game gets, so it's the same when running headless.
+pub struct Input {
+    /// Whether holding a key sends repeated `rock.keypressed` events, off by default like in Love2D
+    pub key_repeat: bool,
+    pub bindings: Bindings,
+    pub gamepads: Gamepads,
+    pub deadzone: f32,
+
+    keys_down: HashSet<String>,
+    scancodes_down: HashSet<String>,
+    mouse_down: HashSet<u8>,
+    mouse_position: (i32, i32),
+    joysticks: BTreeMap<u32, String>,
+    gamepad_buttons: HashSet<(u32, String)>,
+    gamepad_axes: HashMap<(u32, String), f32>,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self {
+            key_repeat: false,
+            bindings: Bindings::default(),
+            gamepads: Gamepads::new(),
+            deadzone: DEFAULT_DEADZONE,
+
+            keys_down: HashSet::new(),
+            scancodes_down: HashSet::new(),
+            mouse_down: HashSet::new(),
+            mouse_position: (0, 0),
+            joysticks: BTreeMap::new(),
+            gamepad_buttons: HashSet::new(),
+            gamepad_axes: HashMap::new(),
+        }
+    }
+
+    /// Updates which inputs are held down.
+    pub fn update(&mut self, event: &RockEvent) {
+        match event {
+            RockEvent::KeyPressed { key, scancode, .. } => {
+                self.keys_down.insert(key.clone());
+                self.scancodes_down.insert(scancode.clone());
+            },
+            RockEvent::KeyReleased { key, scancode } => {
+                self.keys_down.remove(key);
+                self.scancodes_down.remove(scancode);
+            },
+            RockEvent::MousePressed { x, y, button, .. } => {
+                self.mouse_down.insert(*button);
+                self.mouse_position = (*x, *y);
+            },
+            RockEvent::MouseReleased { x, y, button, .. } => {
+                self.mouse_down.remove(button);
+                self.mouse_position = (*x, *y);
+            },
+            RockEvent::MouseMoved { x, y, .. } => { self.mouse_position = (*x, *y); },
+            RockEvent::JoystickAdded { id, name } => { self.joysticks.insert(*id, name.clone()); },
+            RockEvent::JoystickRemoved { id } => {
+                self.joysticks.remove(id);
+                self.gamepad_buttons.retain(|(joystick, _)| joystick != id);
+                self.gamepad_axes.retain(|(joystick, _), _| joystick != id);
+            },
+            RockEvent::GamepadPressed { id, button } => { self.gamepad_buttons.insert((*id, button.clone())); },
+            RockEvent::GamepadReleased { id, button } => { self.gamepad_buttons.remove(&(*id, button.clone())); },
+            RockEvent::GamepadAxis { id, axis, value } => { self.gamepad_axes.insert((*id, axis.clone()), *value); },
+            //Inputs released while the window isn't focused never get reported
+            RockEvent::Focus(false) => {
+                self.keys_down.clear();
+                self.scancodes_down.clear();
+                self.mouse_down.clear();
+            },
+            _ => {},
+        }
+    }
+
+    /// Whether a key (by name, like in `rock.keypressed`) is held down.
+    pub fn is_key_down(&self, key: &str) -> bool {
+        self.keys_down.contains(key)
+    }
+
+    pub fn is_scancode_down(&self, scancode: &str) -> bool {
+        self.scancodes_down.contains(scancode)
+    }
+
+    pub fn is_mouse_down(&self, button: u8) -> bool {
+        self.mouse_down.contains(&button)
+    }
+
+    /// Where the mouse was at its last event, in window coordinates.
+    pub fn mouse_position(&self) -> (i32, i32) {
+        self.mouse_position
+    }
+
+    /// How far a binding is pressed, between 0 and 1.
+    pub fn binding_value(&self, binding: &Binding) -> f32 {
+        let pressed = match binding {
+            Binding::Key(key) => self.keys_down.contains(key),
+            Binding::MouseButton(button) => self.mouse_down.contains(button),
+            Binding::GamepadButton(button) => self.gamepad_buttons.iter().any(|(_, pressed)| pressed == button),
+            //Any gamepad can drive the binding, the one pushed furthest wins
+            Binding::GamepadAxis { axis, positive } => {
+                let sign = if *positive { 1.0 } else { -1.0 };
+                return self.gamepad_axes.iter()
+                    .filter(|((_, name), _)| name == axis)
+                    .map(|(_, value)| (value * sign).max(0.0))
+                    .fold(0.0, f32::max);
+            },
+        };
+        if pressed { 1.0 } else { 0.0 }
+    }
+
+    /// Applies the deadzone to a gamepad axis value, rescaling what's left to the full range.
+    pub fn apply_deadzone(&self, value: f32) -> f32 {
+        let deadzone = self.deadzone.max(0.0).min(0.99);
+        if value.abs() < deadzone {
+            return 0.0;
+        }
+        (value.signum() * (value.abs() - deadzone) / (1.0 - deadzone)).max(-1.0).min(1.0)
+    }
+
+    /// Applies the deadzone to a new value for a gamepad axis,
+    /// returning `None` if the axis doesn't change because of it.
+    pub fn filter_axis(&self, id: u32, axis: &str, value: f32) -> Option<f32> {
+        let value = self.apply_deadzone(value);
+        if value == self.gamepad_axis(id, axis) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Ids of the connected joysticks, with their names.
+    pub fn joysticks(&self) -> &BTreeMap<u32, String> {
+        &self.joysticks
+    }
+
+    pub fn is_gamepad_down(&self, id: u32, button: &str) -> bool {
+        self.gamepad_buttons.contains(&(id, button.to_owned()))
+    }
+
+    /// The gamepad axis' value (with the deadzone applied), or 0 if it didn't move yet.
+    pub fn gamepad_axis(&self, id: u32, axis: &str) -> f32 {
+        self.gamepad_axes.get(&(id, axis.to_owned())).copied().unwrap_or(0.0)
+    }
+
+    /// Whether any input bound to the action is held down. Unknown actions are never down.
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.bindings.actions.get(action)
+            .map_or(false, |bindings| bindings.iter().any(|binding| self.binding_value(binding) > 0.0))
+    }
+
+    /// The axis' value between -1 and 1. Unknown axes are always 0.
+    pub fn axis(&self, axis: &str) -> f32 {
+        let max = |bindings: &[Binding]| bindings.iter().map(|binding| self.binding_value(binding)).fold(0.0, f32::max);
+        match self.bindings.axes.get(axis) {
+            Some(axis) => (max(&axis.positive) - max(&axis.negative)).max(-1.0).min(1.0),
+            None => 0.0,
+        }
+    }
+
+    /// Writes the bindings to the save directory.
+    pub fn save_bindings(&self, vfs: &dyn VirtualFileSystem) -> Result<()> {
+        vfs::write_typed(vfs, BINDINGS_FILE, &self.bindings)?;
+        Ok(())
+    }
+
+    /// Replaces the bindings with the saved ones, if there are any.
+    pub fn load_bindings(&mut self, vfs: &dyn VirtualFileSystem) -> Result<bool> {
+        if !vfs.exists(BINDINGS_FILE) {
+            return Ok(false);
+        }
+        self.bindings = vfs::read_typed(vfs, BINDINGS_FILE)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str, pressed: bool) -> RockEvent {
+        if pressed {
+            RockEvent::KeyPressed { key: name.to_owned(), scancode: name.to_owned(), repeat: false }
+        } else {
+            RockEvent::KeyReleased { key: name.to_owned(), scancode: name.to_owned() }
+        }
+    }
+
+    fn stick(value: f32) -> RockEvent {
+        RockEvent::GamepadAxis { id: 0, axis: "leftx".to_owned(), value: value }
+    }
+
+    #[test]
+    fn axes_combine_their_bindings() {
+        let mut input = Input::new();
+        input.bindings.axes.insert("move_x".to_owned(), AxisBindings {
+            negative: vec!["key:a".parse().unwrap(), "axis:leftx-".parse().unwrap()],
+            positive: vec!["key:d".parse().unwrap(), "axis:leftx+".parse().unwrap()],
+        });
+        assert_eq!(input.axis("move_x"), 0.0);
+        assert_eq!(input.axis("move_y"), 0.0);
+
+        input.update(&key("a", true));
+        assert_eq!(input.axis("move_x"), -1.0);
+        input.update(&key("d", true));
+        assert_eq!(input.axis("move_x"), 0.0);
+        input.update(&key("a", false));
+        assert_eq!(input.axis("move_x"), 1.0);
+
+        //The input pushed furthest in a direction wins
+        input.update(&stick(0.5));
+        assert_eq!(input.axis("move_x"), 1.0);
+        input.update(&key("d", false));
+        assert_eq!(input.axis("move_x"), 0.5);
+        input.update(&stick(-0.25));
+        assert_eq!(input.axis("move_x"), -0.25);
+        input.update(&key("d", true));
+        assert_eq!(input.axis("move_x"), 0.75);
+    }
+}
@@ -0,0 +1,553 @@
+#[macro_use] extern crate log;
+
+use sdl2::event::Event;
+use sdl2::keyboard::{Keycode, Mod};
+use std::time::Instant;
+
+use imgui::im_str;
+
+use mlua::ToLuaMulti;
+
+pub mod lua_api;
+pub mod graphics;
+pub mod math;
+pub mod vfs;
+pub mod assets;
+pub mod config;
+pub mod error;
+pub mod context;
+pub mod builder;
+pub mod events;
+pub mod input;
+pub mod replay;
+
+use lua_api::LuaApi;
+use vfs::VirtualFileSystem;
+use assets::AssetKind;
+use config::Config;
+use context::Context;
+use events::RockEvent;
+use input::QUIT_ACTION;
+use replay::{Recorder, Replay};
+
+pub use builder::RockBuilder;
+
+/// Entry point of the game's Lua code.
+pub const MAIN_SCRIPT: &str = "main.lua";
+
+const BG_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+const ERROR_BG_COLOR: [f32; 4] = [0.35, 0.62, 0.86, 1.0];
+
+/// Options for running without a window or GPU.
+#[derive(Clone)]
+pub struct HeadlessOptions {
+    /// Simulated time between frames, in seconds
+    pub dt: f32,
+    /// Amount of frames to run before stopping
+    pub frames: u64,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            dt: 1.0 / 60.0,
+            frames: 60,
+        }
+    }
+}
+
+/// The debug UI drawn on top of the game, only there when running with a window.
+pub struct DebugUi {
+    pub imgui: imgui::Context,
+    pub imgui_sdl2: imgui_sdl2::ImguiSdl2,
+    pub renderer: imgui_opengl_renderer::Renderer,
+}
+
+/// A running game. Create one with `Rock::builder()`,
+/// then drive it with `run`, `run_headless` or `step_frame`.
+pub struct Rock {
+    pub lua: LuaApi,
+    pub ctx: Context,
+    pub ui: Option<DebugUi>,
+    pub config: Config,
+
+    /// Shown instead of the game when there is no game to run
+    pub message_screen: Option<String>,
+    /// Shown instead of the game after a Lua error, until the code gets fixed
+    pub error_screen: Option<String>,
+    /// Whether `rock.load()` ran without errors, it's retried when fixing the code until it does
+    loaded: bool,
+
+    /// Time not yet simulated by fixed updates, in seconds
+    accumulator: f32,
+    recorder: Option<Recorder>,
+    /// While replaying, the game gets the recorded events instead of live ones
+    replay: Option<Replay>,
+}
+
+impl Rock {
+    /// Starts setting up an engine.
+    pub fn builder() -> RockBuilder {
+        RockBuilder::new()
+    }
+
+    /// Calls `rock.<func_name>(args)`, switching to the error screen when it fails.
+    /// Does nothing while the error screen is shown.
+    pub fn call_game<A>(&mut self, func_name: &str, args: A)
+    where
+        A: for<'lua> ToLuaMulti<'lua>
+    {
+        if self.error_screen.is_some() {
+            return;
+        }
+        if let Err(e) = lua_api::call_rock_func(&self.lua, func_name, args) {
+            self.show_error(e);
+        }
+    }
+
+    /// Passes an event to the game's callbacks, and returns whether the engine should quit.
+    /// Pressing the `quit` action counts as a `Quit` event.
+    /// Gamepad axes get their deadzone applied here, so stick noise never reaches the game.
+    /// While the error screen is shown the game gets no events, and quitting can't be cancelled.
+    pub fn handle_event(&mut self, event: &RockEvent) -> bool {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record_event(event);
+        }
+
+        let filtered_axis;
+        let event = match event {
+            RockEvent::GamepadAxis { id, axis, value } => match self.ctx.borrow().input.filter_axis(*id, axis, *value) {
+                Some(value) => {
+                    filtered_axis = RockEvent::GamepadAxis { id: *id, axis: axis.clone(), value: value };
+                    &filtered_axis
+                },
+                None => return false,
+            },
+            event => event,
+        };
+        let quit_pressed = {
+            let mut ctx = self.ctx.borrow_mut();
+            let was_down = ctx.input.is_action_down(QUIT_ACTION);
+            ctx.input.update(event);
+            !was_down && ctx.input.is_action_down(QUIT_ACTION)
+        };
+        self.dispatch_event(event) || (quit_pressed && self.dispatch_event(&RockEvent::Quit))
+    }
+
+    fn dispatch_event(&mut self, event: &RockEvent) -> bool {
+        if self.error_screen.is_some() {
+            return *event == RockEvent::Quit;
+        }
+        if let RockEvent::KeyPressed { repeat: true, .. } = event {
+            if !self.ctx.borrow().input.key_repeat {
+                return false;
+            }
+        }
+        match event.dispatch(&self.lua) {
+            Ok(quit) => quit,
+            Err(e) => {
+                self.show_error(e);
+                false
+            },
+        }
+    }
+
+    /// Switches to the error screen, showing `e` and its traceback.
+    /// The game can customize the message with `rock.errorhandler(msg)`.
+    pub fn show_error(&mut self, e: mlua::Error) {
+        let mut message = lua_api::error_message(&e);
+        match lua_api::call_error_handler(&self.lua, &message) {
+            Ok(Some(handled)) => message = handled,
+            Ok(None) => {},
+            Err(handler_error) => message = format!("{}\n\nError in `rock.errorhandler`:\n{}", message, lua_api::error_message(&handler_error)),
+        }
+        let mut ctx = self.ctx.borrow_mut();
+        let message = error::annotate_traceback(&message, &ctx.vfs);
+        error!("{}", message);
+        self.error_screen = Some(message);
+        if let Some(graphics) = ctx.graphics.as_mut() {
+            graphics.set_clear_color(ERROR_BG_COLOR);
+        }
+    }
+
+    /// Copies the error screen's message to the clipboard.
+    pub fn copy_error(&self) {
+        if let (Some(message), Some(graphics)) = (&self.error_screen, &self.ctx.borrow().graphics) {
+            let clipboard = graphics.surface.sdl().video().map(|video| video.clipboard());
+            if let Err(e) = clipboard.and_then(|clipboard| clipboard.set_clipboard_text(message)) {
+                error!("Failed to copy error to clipboard: {}", e);
+            }
+        }
+    }
+
+    /// Leaves the error screen, going back to the game.
+    pub fn clear_error(&mut self) {
+        if self.error_screen.take().is_some() {
+            if let Some(graphics) = self.ctx.borrow_mut().graphics.as_mut() {
+                graphics.set_clear_color(BG_COLOR);
+            }
+        }
+    }
+
+    /// Reloads assets whose files changed on disk,
+    /// and calls `rock.filechanged(path)` for each of them.
+    pub fn reload_assets(&mut self) {
+        let changed = {
+            let mut ctx = self.ctx.borrow_mut();
+            let ctx = &mut *ctx;
+            ctx.assets.poll(&ctx.vfs)
+        };
+        for path in changed {
+            info!("Reloading `{}`", path);
+            let kind = self.ctx.borrow().assets.kind(&path);
+            match kind {
+                Some(AssetKind::Mesh) => {
+                    let mut ctx = self.ctx.borrow_mut();
+                    let ctx = &mut *ctx;
+                    let surface = ctx.graphics.as_mut().map(|graphics| &mut graphics.surface);
+                    if let Err(e) = ctx.assets.reload_mesh(&ctx.vfs, surface, &path) {
+                        error!("Failed to reload mesh `{}`: {}", path, e);
+                    }
+                },
+                Some(AssetKind::Shader) => {
+                    let mut ctx = self.ctx.borrow_mut();
+                    let ctx = &mut *ctx;
+                    if let Some(graphics) = ctx.graphics.as_mut() {
+                        match ctx.assets.reload_shader(&ctx.vfs, &mut graphics.surface) {
+                            Some(Ok(program)) => graphics.cur_program = program,
+                            Some(Err(e)) => error!("Failed to reload shader `{}`: {}", path, e),
+                            None => {},
+                        }
+                    }
+                },
+                Some(AssetKind::Script) => {
+                    let mut bytes = Vec::new();
+                    //The context can't stay borrowed while the code runs
+                    let read = self.ctx.borrow().vfs.read_bytes(&path, &mut bytes);
+                    let result = read.map_err(mlua::Error::external)
+                        .and_then(|_| lua_api::reload_code(&self.lua, &format!("@{}", path), &bytes));
+                    match result {
+                        Ok(()) => {
+                            self.clear_error();
+                            //A game that failed to start still has to be loaded once it's fixed
+                            if !self.loaded {
+                                self.load();
+                            }
+                        },
+                        Err(e) => self.show_error(e),
+                    }
+                },
+                Some(AssetKind::Other) | None => {},
+            }
+            if let Err(e) = lua_api::call_optional_rock_func(&self.lua, "filechanged", path.as_str()) {
+                error!("Failed to call `rock.filechanged`: {}", e);
+            }
+        }
+    }
+
+    /// Calls `rock.load()`, which should happen once before the first frame.
+    /// If it fails (or the game failed to start), it gets called again after the code is fixed.
+    pub fn load(&mut self) {
+        self.call_game("load", ());
+        self.loaded = self.error_screen.is_none();
+    }
+
+    /// Runs a single frame of the game: hot-reloading, `rock.update(dt)` and `rock.draw()`.
+    /// With a fixed timestep, `rock.fixedupdate(step)` gets called for every step
+    /// that fits in the elapsed time, and `rock.draw(alpha)` gets the interpolation factor.
+    /// During a replay, the next recorded frame gets played instead, with its recorded delta time.
+    ///
+    /// With a window, the window's events get handled first, and the frame gets shown
+    /// (along with the debug UI) at the end, so a windowed engine can be driven with this too.
+    /// Returns whether the game quit. Only fails when the renderer does, Lua errors go to the error screen.
+    pub fn step_frame(&mut self, dt: f32) -> error::Result<bool> {
+        let mut ui = match self.ui.take() {
+            Some(ui) => ui,
+            None => return self.advance(dt),
+        };
+        let result = self.step_window_frame(&mut ui, dt);
+        self.ui = Some(ui);
+        result
+    }
+
+    /// Plays the next frame of the replay if there is one, or a frame with live input otherwise.
+    /// Returns whether the game quit.
+    fn advance(&mut self, dt: f32) -> error::Result<bool> {
+        match self.step_replay()? {
+            Some(quit) => Ok(quit),
+            None => {
+                self.update_and_draw(dt)?;
+                Ok(false)
+            },
+        }
+    }
+
+    /// Updates and draws the game, without touching the window.
+    fn update_and_draw(&mut self, dt: f32) -> error::Result<()> {
+        //Recorded before running it, so a crash during the frame still ends up in the recording
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record_frame(&self.ctx.borrow().vfs, dt) {
+                warn!("Stopped recording: {}", e);
+                self.recorder = None;
+            }
+        }
+
+        //Hot-reload changed assets
+        self.reload_assets();
+
+        let alpha = if self.config.timestep.fixed {
+            Some(self.fixed_update(dt))
+        } else {
+            None
+        };
+
+        //Call game update
+        self.call_game("update", dt);
+
+        //Clear screen with correct color
+        if let Some(graphics) = self.ctx.borrow_mut().graphics.as_mut() {
+            graphics.clear()?;
+        }
+
+        //Call game draw
+        match alpha {
+            Some(alpha) => self.call_game("draw", alpha),
+            None => self.call_game("draw", ()),
+        }
+        Ok(())
+    }
+
+    /// Whether the game is being fed a recording instead of live input.
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Plays the next frame of the replay: its events, then the frame itself with its recorded delta time.
+    /// Returns whether the game quit, or `None` once there is nothing (left) to replay.
+    pub fn step_replay(&mut self) -> error::Result<Option<bool>> {
+        let frame = match self.replay.as_mut().and_then(Replay::next_frame) {
+            Some(frame) => frame,
+            None => {
+                if self.replay.take().is_some() {
+                    info!("Replay finished");
+                }
+                return Ok(None);
+            },
+        };
+        for event in &frame.events {
+            if self.handle_event(event) {
+                return Ok(Some(true));
+            }
+        }
+        self.update_and_draw(frame.dt)?;
+        Ok(Some(false))
+    }
+
+    /// Calls `rock.fixedupdate(step)` until the elapsed time is used up,
+    /// and returns how far the game is into the next step (between 0 and 1).
+    fn fixed_update(&mut self, dt: f32) -> f32 {
+        let step = self.config.timestep.step();
+        self.accumulator += dt;
+        let mut steps = 0;
+        while self.accumulator >= step {
+            //Spiral of death: when the updates can't keep up, drop the time they couldn't simulate
+            if steps >= self.config.timestep.max_steps {
+                self.accumulator %= step;
+                break;
+            }
+            self.call_game("fixedupdate", step);
+            self.accumulator -= step;
+            steps += 1;
+        }
+        self.accumulator / step
+    }
+
+    /// Runs the game without a window for a fixed amount of frames, with a simulated delta time.
+    /// Returns the error message if the game fails, so scripts can be tested automatically.
+    pub fn run_headless(&mut self, options: &HeadlessOptions) -> Result<(), String> {
+        if let Some(message) = &self.message_screen {
+            return Err(message.clone());
+        }
+
+        //Call program load
+        self.load();
+
+        //A replay decides how many frames get run, and how long each one takes
+        if self.is_replaying() {
+            while let Some(quit) = self.step_replay().map_err(|e| e.to_string())? {
+                if quit || self.error_screen.is_some() {
+                    break;
+                }
+            }
+        } else {
+            for _ in 0..options.frames {
+                if let Some(message) = &self.error_screen {
+                    return Err(message.clone());
+                }
+                if self.step_frame(options.dt).map_err(|e| e.to_string())? {
+                    break;
+                }
+            }
+        }
+        match &self.error_screen {
+            Some(message) => Err(message.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs the game in its window until it gets closed.
+    pub fn run(&mut self) {
+        assert!(self.ui.is_some(), "Can't run a headless engine in a window!");
+
+        let mut start_t = Instant::now();
+        let mut deltatime = 0.0; //In seconds
+
+        // let triangle = graphics::g2d::primitives::create_triangle(&mut self.ctx.borrow_mut().graphics.surface);
+
+        //Call program load
+        self.load();
+
+        loop {
+            match self.step_frame(deltatime) {
+                Ok(false) => {},
+                Ok(true) => break,
+                Err(e) => {
+                    error!("{}", e);
+                    break;
+                },
+            }
+
+            deltatime = start_t.elapsed().as_secs_f32();
+            start_t = Instant::now();
+            // warn!("dt: {}", deltatime);
+        }
+    }
+
+    /// Runs a frame in the window: handles its events, runs the game and shows the result.
+    /// Returns whether the game quit.
+    fn step_window_frame(&mut self, ui: &mut DebugUi, deltatime: f32) -> error::Result<bool> {
+        //Collected first, as the context can't stay borrowed while the game handles them
+        let events: Vec<Event> = self.ctx.borrow_mut().graphics.as_mut().unwrap().event_pump.poll_iter().collect();
+        let replaying = self.is_replaying();
+        for event in events {
+            ui.imgui_sdl2.handle_event(&mut ui.imgui, &event);
+            if ui.imgui_sdl2.ignore_event(&event) { continue; }
+
+            match event {
+                Event::KeyDown { keycode: Some(Keycode::C), keymod, .. } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.copy_error();
+                },
+                _ => {}
+            }
+
+            //The game only gets the recorded events during a replay, but closing the window still works
+            if replaying {
+                if let Event::Quit { .. } = event {
+                    return Ok(true);
+                }
+                continue;
+            }
+
+            //Gamepads get opened (and closed) before the game hears about them
+            let rock_event = match event {
+                Event::ControllerDeviceAdded { which, .. } => self.ctx.borrow_mut().input.gamepads.open(which),
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.ctx.borrow_mut().input.gamepads.close(which);
+                    RockEvent::from_sdl(&event)
+                },
+                _ => RockEvent::from_sdl(&event),
+            };
+            if let Some(rock_event) = rock_event {
+                if self.handle_event(&rock_event) {
+                    return Ok(true);
+                }
+            }
+        }
+
+        if self.advance(deltatime)? {
+            return Ok(true);
+        }
+
+        //IMGUI UI
+        //The first frame has no delta time yet, which IMGUI doesn't accept
+        if deltatime > 0.0 {
+            ui.imgui.io_mut().delta_time = deltatime;
+        }
+        let mut copy_error = false;
+        {
+            let ctx = self.ctx.borrow();
+            let graphics = ctx.graphics.as_ref().unwrap();
+            ui.imgui_sdl2.prepare_frame(ui.imgui.io_mut(), &graphics.surface.window(), &graphics.event_pump.mouse_state());
+            let imgui_ui = ui.imgui.frame();
+            if self.config.debug {
+                let perf_window = imgui::Window::new(im_str!("Performance"))
+                            .position([5.0, 5.0], imgui::Condition::Appearing)
+                            .size([180.0, 90.0], imgui::Condition::Appearing)
+                            .resizable(true)
+                            .title_bar(true);
+                perf_window.build(&imgui_ui, || {
+                    imgui_ui.text(im_str!("FPS: {:.2} ({:.1}ms)", 1.0 / deltatime, deltatime * 1000.0));
+                    imgui_ui.separator();
+                    imgui_ui.text(im_str!("Tris: {}", graphics.tri_count));
+                    imgui_ui.text(im_str!("Drawcalls: {}", graphics.draw_calls));
+                });
+                let debug_window = imgui::Window::new(im_str!("Debug"))
+                            .position([5.0, 100.0], imgui::Condition::Appearing)
+                            .size([180.0, 80.0], imgui::Condition::Appearing)
+                            .resizable(true)
+                            .title_bar(true);
+                debug_window.build(&imgui_ui, || {
+                    match &self.replay {
+                        Some(replay) => imgui_ui.text(im_str!("Replaying: {} frames left", replay.frames_left())),
+                        None if self.recorder.is_some() => imgui_ui.text("Recording"),
+                        None => imgui_ui.text("Nothing here yet :)"),
+                    }
+                });
+            }
+            if let Some(message) = &self.message_screen {
+                let message_window = imgui::Window::new(im_str!("Rock"))
+                            .position([200.0, 5.0], imgui::Condition::Appearing)
+                            .size([600.0, 200.0], imgui::Condition::Appearing)
+                            .resizable(true)
+                            .title_bar(true);
+                message_window.build(&imgui_ui, || {
+                    imgui_ui.text(message);
+                });
+            }
+
+            if let Some(message) = &self.error_screen {
+                let [width, height] = imgui_ui.io().display_size;
+                let error_window = imgui::Window::new(im_str!("Error"))
+                            .position([0.0, 0.0], imgui::Condition::Always)
+                            .size([width, height], imgui::Condition::Always)
+                            .bg_alpha(0.0)
+                            .movable(false)
+                            .resizable(false)
+                            .collapsible(false)
+                            .title_bar(false);
+                error_window.build(&imgui_ui, || {
+                    imgui_ui.text(im_str!("Error"));
+                    imgui_ui.separator();
+                    imgui_ui.text_wrapped(&imgui::ImString::new(message.as_str()));
+                    imgui_ui.separator();
+                    if imgui_ui.button(im_str!("Copy to clipboard"), [0.0, 0.0]) {
+                        copy_error = true;
+                    }
+                    imgui_ui.same_line(0.0);
+                    imgui_ui.text(im_str!("(or press Ctrl+C), fix the code and save to reload"));
+                });
+            }
+
+            //Render IMGUI
+            ui.imgui_sdl2.prepare_render(&imgui_ui, &graphics.surface.window());
+            ui.renderer.render(imgui_ui);
+        }
+        if copy_error {
+            self.copy_error();
+        }
+
+        // surface.window().set_title(&format!("fps: {:.2}", 1.0 / deltatime));
+        self.ctx.borrow().graphics.as_ref().unwrap().surface.window().gl_swap_window();
+        Ok(false)
+    }
+}
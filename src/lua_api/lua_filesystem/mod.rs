@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::time::UNIX_EPOCH;
+
+use mlua::{Lua, Table, Result, Value, FromLua};
+
+use super::{LuaApi, with_context};
+use crate::vfs::{VirtualFileSystem, VfsPath, mount_vfs::MountVFS};
+use crate::assets::AssetKind;
+use crate::error::RockError;
+
+/// Lets Lua functions take sandboxed paths as arguments directly.
+impl<'lua> FromLua<'lua> for VfsPath {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> Result<Self> {
+        let path = String::from_lua(value, lua)?;
+        VfsPath::new(path.as_str()).map_err(vfs_error)
+    }
+}
+
+/// Turns VFS errors into Lua errors scripts can `pcall`.
+fn vfs_error(e: std::io::Error) -> mlua::Error {
+    RockError::Vfs(e).into()
+}
+
+/// Runs `func` on the engine's VFS, turning its errors into Lua errors.
+fn with_vfs<R, F>(lua: &Lua, func: F) -> Result<R>
+where
+    F: FnOnce(&MountVFS) -> std::io::Result<R>
+{
+    with_context(lua, |ctx| func(&ctx.vfs).map_err(vfs_error))
+}
+
+///Loads rock.filesystem
+pub fn load_filesystem_table(lua: &LuaApi) -> Result<()> {
+    let filesystem_table = lua.create_table()?;
+
+    let read_func = lua.create_function(|lua, path: VfsPath| {
+        let mut bytes = Vec::new();
+        with_vfs(lua, |vfs| vfs.read_bytes(path.as_str(), &mut bytes))?;
+        let size = bytes.len();
+        Ok((lua.create_string(&bytes)?, size))
+    })?;
+    filesystem_table.set("read", read_func)?;
+    let write_func = lua.create_function(|lua, (path, data): (VfsPath, mlua::String)| {
+        with_vfs(lua, |vfs| vfs.write_bytes(path.as_str(), data.as_bytes()))
+    })?;
+    filesystem_table.set("write", write_func)?;
+    let append_func = lua.create_function(|lua, (path, data): (VfsPath, mlua::String)| {
+        with_vfs(lua, |vfs| vfs.append_bytes(path.as_str(), data.as_bytes()))
+    })?;
+    filesystem_table.set("append", append_func)?;
+    let lines_func = lua.create_function(|lua, path: VfsPath| {
+        let mut bytes = Vec::new();
+        with_vfs(lua, |vfs| vfs.read_bytes(path.as_str(), &mut bytes))?;
+        let text = String::from_utf8_lossy(&bytes);
+        let lines: Vec<String> = text.lines().map(|line| line.to_owned()).collect();
+        let iter = RefCell::new(lines.into_iter());
+        lua.create_function(move |_, ()| {
+            Ok(iter.borrow_mut().next())
+        })
+    })?;
+    filesystem_table.set("lines", lines_func)?;
+    let exists_func = lua.create_function(|lua, path: VfsPath| {
+        with_vfs(lua, |vfs| Ok(vfs.exists(path.as_str())))
+    })?;
+    filesystem_table.set("exists", exists_func)?;
+    let get_info_func = lua.create_function(|lua, path: VfsPath| {
+        let meta = match with_context(lua, |ctx| Ok(ctx.vfs.metadata(path.as_str())))? {
+            Ok(meta) => meta,
+            Err(_) => return Ok(Value::Nil),
+        };
+        let info = lua.create_table()?;
+        info.set("type", if meta.is_dir { "directory" } else { "file" })?;
+        info.set("size", meta.size)?;
+        if let Some(modified) = meta.modified {
+            if let Ok(since_epoch) = modified.duration_since(UNIX_EPOCH) {
+                info.set("modtime", since_epoch.as_secs())?;
+            }
+        }
+        Ok(Value::Table(info))
+    })?;
+    filesystem_table.set("getInfo", get_info_func)?;
+    let get_directory_items_func = lua.create_function(|lua, path: VfsPath| {
+        with_vfs(lua, |vfs| vfs.list_dir(path.as_str()))
+    })?;
+    filesystem_table.set("getDirectoryItems", get_directory_items_func)?;
+    let create_directory_func = lua.create_function(|lua, path: VfsPath| {
+        with_vfs(lua, |vfs| vfs.create_dir(path.as_str()))
+    })?;
+    filesystem_table.set("createDirectory", create_directory_func)?;
+    let remove_func = lua.create_function(|lua, path: VfsPath| {
+        with_vfs(lua, |vfs| vfs.remove(path.as_str()))
+    })?;
+    filesystem_table.set("remove", remove_func)?;
+    let load_func = lua.create_function(|lua, path: VfsPath| {
+        let mut bytes = Vec::new();
+        with_vfs(lua, |vfs| vfs.read_bytes(path.as_str(), &mut bytes))?;
+        let chunk = lua.load(&bytes).set_name(&format!("@{}", path.as_str()))?.into_function()?;
+        //Not executed again when they change, as nothing would get what they return,
+        //but the game hears about it through `rock.filechanged`
+        with_context(lua, |ctx| {
+            ctx.assets.watch(&ctx.vfs, path.as_str(), AssetKind::Other);
+            Ok(())
+        })?;
+        Ok(chunk)
+    })?;
+    filesystem_table.set("load", load_func)?;
+    let watch_func = lua.create_function(|lua, path: VfsPath| {
+        with_context(lua, |ctx| {
+            ctx.assets.watch(&ctx.vfs, path.as_str(), AssetKind::Other);
+            Ok(())
+        })
+    })?;
+    filesystem_table.set("watch", watch_func)?;
+    let get_save_directory_func = lua.create_function(|lua, ()| {
+        with_vfs(lua, |vfs| Ok(vfs.save_dir().map(|dir| dir.to_string_lossy().into_owned())))
+    })?;
+    filesystem_table.set("getSaveDirectory", get_save_directory_func)?;
+
+    let globals = lua.globals();
+    let rock_table: Table = globals.get("rock")?;
+    rock_table.set("filesystem", filesystem_table)?;
+    Ok(())
+}
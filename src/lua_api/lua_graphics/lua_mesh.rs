@@ -1,5 +1,8 @@
+use std::cell::Ref;
+
 use luminance::tess::{Tess, Mode};
 use luminance_gl::GL33;
+use luminance_sdl2::GL33Surface;
 
 use mlua::{Chunk, Function, Table, Lua, prelude::ToLua, MetaMethod, Result, UserData, UserDataMethods, Variadic};
 
@@ -16,15 +19,11 @@ pub struct LuaMesh {
 }
 
 impl LuaMesh {
-    pub fn new(vertices: &[VertexType]) -> Self {
-        let mesh = unsafe { Mesh::new(&mut crate::ROCK.as_mut().unwrap().surface, |builder| {
-            builder.set_vertices(vertices)
-                   .set_indices(Vec::<u32>::new())
-                   .set_mode(Mode::Triangle)
-        }) };
-        Self {
+    pub fn new(surface: Option<&mut GL33Surface>, vertices: &[VertexType]) -> Result<Self> {
+        let mesh = Mesh::from_data(surface, vertices, &[])?;
+        Ok(Self {
             mesh: mesh,
-        }
+        })
     }
 
     pub fn from_mesh(mesh: Mesh) -> Self {
@@ -33,7 +32,7 @@ impl LuaMesh {
         }
     }
 
-    pub fn tess(&self) -> &Tess<GL33, VertexType, u32> {
+    pub fn tess(&self) -> Option<Ref<Tess<GL33, VertexType, u32>>> {
         self.mesh.tess()
     }
 }
@@ -46,7 +45,7 @@ impl UserData for LuaMesh {
     }
 }
 
-pub fn mesh_constructor(lua_verts: Table) -> Result<LuaMesh> {
+pub fn mesh_constructor(lua: &Lua, lua_verts: Table) -> Result<LuaMesh> {
     let mut vertices = Vec::new();
     for i in 0..lua_verts.len()? {
         let lua_vert: Table = lua_verts.get(i + 1)?;
@@ -64,5 +63,5 @@ pub fn mesh_constructor(lua_verts: Table) -> Result<LuaMesh> {
         );
         vertices.push(vert);
     }
-    Ok(LuaMesh::new(&vertices))
+    crate::lua_api::with_context(lua, |ctx| LuaMesh::new(ctx.surface(), &vertices))
 }
@@ -6,7 +6,11 @@ use mlua::{Chunk, Function, Table, Lua, prelude::ToLua, MetaMethod, Result, User
 pub mod lua_mesh;
 pub mod lua_material;
 
-use super::LuaApi;
+use super::{LuaApi, with_context};
+use crate::error::RockError;
+use crate::context::EngineContext;
+use crate::graphics::Graphics;
+use crate::vfs::{VirtualFileSystem, VfsPath};
 
 use lua_mesh::{LuaMesh, mesh_constructor};
 use super::lua_math::{LuaTransform, LuaVec2,LuaVec3};
@@ -15,22 +19,21 @@ use super::lua_math::{LuaTransform, LuaVec2,LuaVec3};
 pub fn load_graphics_table(lua: &LuaApi) -> Result<()> {
     let graphics_table = lua.create_table()?;
 
-    let clear_func = lua.create_function(|_,(r,g,b,a)| {
-        clear(r,g,b,a);
-        Ok(())
+    let clear_func = lua.create_function(|lua,(r,g,b,a)| {
+        clear(lua, r,g,b,a)
     })?;
     graphics_table.set("clear", clear_func)?;
-    let mesh_func = lua.create_function(|_,vertices| {
-        mesh_constructor(vertices)
+    let mesh_func = lua.create_function(|lua,vertices| {
+        mesh_constructor(lua, vertices)
     })?;
     graphics_table.set("mesh", mesh_func)?;
-    let load_mesh_func = lua.create_function(|_,(path, format): (String, String)| {
+    let load_mesh_func = lua.create_function(|lua,(path, format): (VfsPath, String)| {
         use crate::graphics::MeshByteFormat;
         let bformat = MeshByteFormat::from_string(format);
-        // let vertices = crate::graphics::g2d::TRIANGLE;
-        let mut bytes = Vec::new();
-        unsafe { crate::ROCK.as_ref().unwrap().vfs.read_bytes(&path, &mut bytes).expect("Failed to load file!"); }
-        let mesh_vec = crate::graphics::meshes_from_bytes(bytes, bformat);
+        let mesh_vec = with_context(lua, |ctx| {
+            let surface = ctx.graphics.as_mut().map(|graphics| &mut graphics.surface);
+            Ok(ctx.assets.load_mesh(&ctx.vfs, surface, path.as_str(), bformat)?)
+        })?;
         let mut meshes = Vec::new();
         let mut transforms = Vec::new();
         for (mesh, transform) in mesh_vec {
@@ -42,9 +45,15 @@ pub fn load_graphics_table(lua: &LuaApi) -> Result<()> {
         Ok((meshes, transforms))
     })?;
     graphics_table.set("load_mesh", load_mesh_func)?;
-    let draw_func = lua.create_function(|_,(mesh, transform)| {
-        draw(mesh, transform);
-        Ok(())
+    let set_shader_func = lua.create_function(|lua,(vs_path, fs_path): (Option<VfsPath>, Option<VfsPath>)| {
+        with_context(lua, |ctx| set_shader(ctx, vs_path, fs_path))
+    })?;
+    graphics_table.set("setShader", set_shader_func)?;
+    let draw_func = lua.create_function(|lua,(mesh, transform)| {
+        with_context(lua, |ctx| match ctx.graphics.as_mut() {
+            Some(graphics) => draw(graphics, mesh, transform),
+            None => Ok(()),
+        })
     })?;
     graphics_table.set("draw", draw_func)?;
 
@@ -54,51 +63,83 @@ pub fn load_graphics_table(lua: &LuaApi) -> Result<()> {
     Ok(())
 }
 
-fn clear(r: f32, g: f32, b: f32, a: f32) {
-    unsafe {
-        if let Some(ref mut rock) = crate::ROCK {
-            rock.pipeline_state = rock.pipeline_state.clone().set_clear_color([r,g,b,a]);
-        } else {
-            panic!("Failed to get `ROCK`!");
+fn clear(lua: &Lua, r: f32, g: f32, b: f32, a: f32) -> Result<()> {
+    with_context(lua, |ctx| {
+        if let Some(graphics) = ctx.graphics.as_mut() {
+            graphics.set_clear_color([r,g,b,a]);
         }
+        Ok(())
+    })
+}
+
+/// Switches to the shader in the given files (which get hot-reloaded),
+/// or back to the default shader when no files are given.
+fn set_shader(ctx: &mut EngineContext, vs_path: Option<VfsPath>, fs_path: Option<VfsPath>) -> Result<()> {
+    let graphics = match ctx.graphics.as_mut() {
+        Some(graphics) => graphics,
+        //Without graphics there's nothing to compile, but missing files should still be noticed
+        None => {
+            for path in vs_path.iter().chain(fs_path.iter()) {
+                if !ctx.vfs.exists(path.as_str()) {
+                    return Err(RockError::Vfs(std::io::Error::new(std::io::ErrorKind::NotFound, format!("`{}` doesn't exist!", path))).into());
+                }
+            }
+            return Ok(());
+        },
+    };
+    match (vs_path, fs_path) {
+        (Some(vs_path), Some(fs_path)) => {
+            let program = ctx.assets.load_shader(&ctx.vfs, &mut graphics.surface, vs_path.as_str(), fs_path.as_str())?;
+            graphics.cur_program = program;
+        },
+        (None, None) => {
+            ctx.assets.clear_shader();
+            graphics.cur_program = crate::graphics::get_default_program(&mut graphics.surface)?;
+        },
+        _ => return Err(mlua::Error::RuntimeError("`setShader` needs both a vertex and a fragment shader!".to_owned())),
     }
+    Ok(())
 }
 
-fn draw(mesh: LuaMesh, transform: LuaTransform) {
-    use crate::ROCK;
+fn draw(graphics: &mut Graphics, mesh: LuaMesh, transform: LuaTransform) -> Result<()> {
     use luminance::render_state::RenderState;
 
-    let back_buffer = unsafe { ROCK.as_mut().unwrap().surface.back_buffer().expect("Failed to get backbuffer!") };
-    let camera = unsafe { &ROCK.as_ref().unwrap().camera };
+    let back_buffer = graphics.surface.back_buffer().map_err(|e| RockError::Gpu(format!("Failed to get backbuffer: {}", e)))?;
+    let render_state = graphics.get_render_state();
+    let aspect_ratio = graphics.aspect_ratio();
+    let camera = &graphics.camera;
+    let program = &mut graphics.cur_program;
+    let tess = match mesh.tess() {
+        Some(tess) => tess,
+        None => return Err(RockError::Gpu("Can't draw a mesh that was created without graphics!".to_owned()).into()),
+    };
 
-    let render = unsafe { ROCK.as_mut().unwrap().surface.new_pipeline_gate().pipeline(
+    let render = graphics.surface.new_pipeline_gate().pipeline(
         &back_buffer,
-        &ROCK.as_mut().unwrap().get_render_state(),
+        &render_state,
         |_pipeline, mut shd_gate| {
-            shd_gate.shade(&mut ROCK.as_mut().unwrap().default_program, |mut iface, uni, mut rdr_gate| {
+            shd_gate.shade(program, |mut iface, uni, mut rdr_gate| {
                 iface.set(&uni.offset, transform.transform.get_matrix().to_cols_array_2d());
 
                 //MVP
-                iface.set(&uni.projection, camera.get_proj().to_cols_array_2d());
+                iface.set(&uni.projection, camera.get_proj(aspect_ratio).to_cols_array_2d());
                 iface.set(&uni.view, camera.get_view().to_cols_array_2d());
                 iface.set(&uni.normal_matrix, transform.transform.get_normal_matrix().to_cols_array_2d());
                 iface.set(&uni.cam_pos, camera.transform.pos.into());
 
                 rdr_gate.render(&RenderState::default(), |mut tess_gate| {
-                    tess_gate.render(mesh.tess())
+                    tess_gate.render(&*tess)
                 })
             })
         },
-    ).assume() };
+    ).assume();
 
     if !render.is_ok() {
-        panic!("Renderer ran into unknown error!");
+        return Err(RockError::Gpu("Renderer ran into unknown error!".to_owned()).into());
     }
 
-    unsafe {
-        // println!("{}", mesh.mesh.tri_count());
-        let r = ROCK.as_mut().unwrap();
-        r.tri_count += mesh.mesh.tri_count();
-        r.draw_calls += 1;
-    }
+    // println!("{}", mesh.mesh.tri_count());
+    graphics.tri_count += mesh.mesh.tri_count();
+    graphics.draw_calls += 1;
+    Ok(())
 }
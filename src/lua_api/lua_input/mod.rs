@@ -0,0 +1,81 @@
+use mlua::{Table, Result};
+
+use super::{LuaApi, with_context};
+use crate::input::{Binding, AxisBindings};
+
+fn parse_bindings(bindings: Vec<String>) -> Result<Vec<Binding>> {
+    bindings.iter().map(|binding| binding.parse().map_err(mlua::Error::RuntimeError)).collect()
+}
+
+fn format_bindings(bindings: &[Binding]) -> Vec<String> {
+    bindings.iter().map(|binding| binding.to_string()).collect()
+}
+
+///Loads rock.input
+pub fn load_input_table(lua: &LuaApi) -> Result<()> {
+    let input_table = lua.create_table()?;
+
+    let is_action_down_func = lua.create_function(|lua, action: String| {
+        with_context(lua, |ctx| Ok(ctx.input.is_action_down(&action)))
+    })?;
+    input_table.set("isActionDown", is_action_down_func)?;
+    let get_axis_func = lua.create_function(|lua, axis: String| {
+        with_context(lua, |ctx| Ok(ctx.input.axis(&axis)))
+    })?;
+    input_table.set("getAxis", get_axis_func)?;
+    //Passing nil removes the action
+    let set_action_bindings_func = lua.create_function(|lua, (action, bindings): (String, Option<Vec<String>>)| {
+        let bindings = bindings.map(parse_bindings).transpose()?;
+        with_context(lua, |ctx| {
+            match bindings {
+                Some(bindings) => { ctx.input.bindings.actions.insert(action, bindings); },
+                None => { ctx.input.bindings.actions.remove(&action); },
+            }
+            Ok(())
+        })
+    })?;
+    input_table.set("setActionBindings", set_action_bindings_func)?;
+    let get_action_bindings_func = lua.create_function(|lua, action: String| {
+        with_context(lua, |ctx| Ok(ctx.input.bindings.actions.get(&action).map(|bindings| format_bindings(bindings))))
+    })?;
+    input_table.set("getActionBindings", get_action_bindings_func)?;
+    //Passing nil for both sides removes the axis
+    let set_axis_bindings_func = lua.create_function(|lua, (axis, negative, positive): (String, Option<Vec<String>>, Option<Vec<String>>)| {
+        let bindings = match (negative, positive) {
+            (None, None) => None,
+            (negative, positive) => Some(AxisBindings {
+                negative: parse_bindings(negative.unwrap_or_default())?,
+                positive: parse_bindings(positive.unwrap_or_default())?,
+            }),
+        };
+        with_context(lua, |ctx| {
+            match bindings {
+                Some(bindings) => { ctx.input.bindings.axes.insert(axis, bindings); },
+                None => { ctx.input.bindings.axes.remove(&axis); },
+            }
+            Ok(())
+        })
+    })?;
+    input_table.set("setAxisBindings", set_axis_bindings_func)?;
+    let get_axis_bindings_func = lua.create_function(|lua, axis: String| {
+        with_context(lua, |ctx| Ok(match ctx.input.bindings.axes.get(&axis) {
+            Some(bindings) => (Some(format_bindings(&bindings.negative)), Some(format_bindings(&bindings.positive))),
+            None => (None, None),
+        }))
+    })?;
+    input_table.set("getAxisBindings", get_axis_bindings_func)?;
+    let save_bindings_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| Ok(ctx.input.save_bindings(&ctx.vfs)?))
+    })?;
+    input_table.set("saveBindings", save_bindings_func)?;
+    //Returns whether there were saved bindings to load
+    let load_bindings_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| Ok(ctx.input.load_bindings(&ctx.vfs)?))
+    })?;
+    input_table.set("loadBindings", load_bindings_func)?;
+
+    let globals = lua.globals();
+    let rock_table: Table = globals.get("rock")?;
+    rock_table.set("input", input_table)?;
+    Ok(())
+}
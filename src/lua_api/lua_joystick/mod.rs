@@ -0,0 +1,77 @@
+use mlua::{Table, Result, Variadic};
+
+use sdl2::controller::{Axis, Button};
+
+use super::{LuaApi, with_context};
+use crate::vfs::{VirtualFileSystem, VfsPath};
+use crate::error::RockError;
+
+fn check_button(button: &str) -> Result<()> {
+    Button::from_string(button).map(|_| ()).ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid gamepad button: {}", button)))
+}
+
+fn check_axis(axis: &str) -> Result<()> {
+    Axis::from_string(axis).map(|_| ()).ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid gamepad axis: {}", axis)))
+}
+
+///Loads rock.joystick
+pub fn load_joystick_table(lua: &LuaApi) -> Result<()> {
+    let joystick_table = lua.create_table()?;
+
+    let get_joysticks_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| Ok(ctx.input.joysticks().keys().copied().collect::<Vec<u32>>()))
+    })?;
+    joystick_table.set("getJoysticks", get_joysticks_func)?;
+    let get_joystick_count_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| Ok(ctx.input.joysticks().len()))
+    })?;
+    joystick_table.set("getJoystickCount", get_joystick_count_func)?;
+    let get_name_func = lua.create_function(|lua, id: u32| {
+        with_context(lua, |ctx| Ok(ctx.input.joysticks().get(&id).cloned()))
+    })?;
+    joystick_table.set("getName", get_name_func)?;
+    let is_gamepad_down_func = lua.create_function(|lua, (id, buttons): (u32, Variadic<String>)| {
+        for button in buttons.iter() {
+            check_button(button)?;
+        }
+        with_context(lua, |ctx| Ok(buttons.iter().any(|button| ctx.input.is_gamepad_down(id, button))))
+    })?;
+    joystick_table.set("isGamepadDown", is_gamepad_down_func)?;
+    let get_gamepad_axis_func = lua.create_function(|lua, (id, axis): (u32, String)| {
+        check_axis(&axis)?;
+        with_context(lua, |ctx| Ok(ctx.input.gamepad_axis(id, &axis)))
+    })?;
+    joystick_table.set("getGamepadAxis", get_gamepad_axis_func)?;
+    let set_deadzone_func = lua.create_function(|lua, deadzone: f32| {
+        with_context(lua, |ctx| {
+            ctx.input.deadzone = deadzone.max(0.0).min(0.99);
+            Ok(())
+        })
+    })?;
+    joystick_table.set("setDeadzone", set_deadzone_func)?;
+    let get_deadzone_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| Ok(ctx.input.deadzone))
+    })?;
+    joystick_table.set("getDeadzone", get_deadzone_func)?;
+    //Takes a file in the SDL GameController database format, returns how many mappings got added
+    let load_gamepad_mappings_func = lua.create_function(|lua, path: VfsPath| {
+        with_context(lua, |ctx| {
+            let mut mappings = Vec::new();
+            ctx.vfs.read_bytes(path.as_str(), &mut mappings).map_err(RockError::Vfs)?;
+            Ok(ctx.input.gamepads.add_mappings(&String::from_utf8_lossy(&mappings)))
+        })
+    })?;
+    joystick_table.set("loadGamepadMappings", load_gamepad_mappings_func)?;
+    //Strengths are between 0 and 1, the duration is in seconds (forever if left out).
+    //Returns false when the gamepad can't vibrate
+    let set_vibration_func = lua.create_function(|lua, (id, left, right, duration): (u32, Option<f32>, Option<f32>, Option<f32>)| {
+        let duration_ms = duration.map_or(u32::MAX, |duration| (duration.max(0.0) * 1000.0) as u32);
+        with_context(lua, |ctx| Ok(ctx.input.gamepads.set_vibration(id, left.unwrap_or(0.0), right.unwrap_or(0.0), duration_ms)))
+    })?;
+    joystick_table.set("setVibration", set_vibration_func)?;
+
+    let globals = lua.globals();
+    let rock_table: Table = globals.get("rock")?;
+    rock_table.set("joystick", joystick_table)?;
+    Ok(())
+}
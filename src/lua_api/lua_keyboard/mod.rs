@@ -0,0 +1,74 @@
+use mlua::{Table, Result, Variadic};
+
+use sdl2::keyboard::{Keycode, Scancode};
+
+use super::{LuaApi, with_context};
+use crate::events::{key_name, scancode_name};
+
+/// The key's name as it shows up in events, so it can be looked up in the held keys.
+fn check_key(key: &str) -> Result<String> {
+    Keycode::from_name(key).map(key_name)
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid key constant: {}", key)))
+}
+
+fn check_scancode(name: &str) -> Result<String> {
+    Scancode::from_name(name).map(scancode_name)
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid scancode: {}", name)))
+}
+
+///Loads rock.keyboard
+pub fn load_keyboard_table(lua: &LuaApi) -> Result<()> {
+    let keyboard_table = lua.create_table()?;
+
+    //Held keys come from the events the game got, so they are the same during replays and when headless
+    let is_down_func = lua.create_function(|lua, keys: Variadic<String>| {
+        let keys = keys.iter().map(|key| check_key(key)).collect::<Result<Vec<_>>>()?;
+        with_context(lua, |ctx| Ok(keys.iter().any(|key| ctx.input.is_key_down(key))))
+    })?;
+    keyboard_table.set("isDown", is_down_func)?;
+    let is_scancode_down_func = lua.create_function(|lua, names: Variadic<String>| {
+        let scancodes = names.iter().map(|name| check_scancode(name)).collect::<Result<Vec<_>>>()?;
+        with_context(lua, |ctx| Ok(scancodes.iter().any(|scancode| ctx.input.is_scancode_down(scancode))))
+    })?;
+    keyboard_table.set("isScancodeDown", is_scancode_down_func)?;
+    let set_key_repeat_func = lua.create_function(|lua, enable: bool| {
+        with_context(lua, |ctx| {
+            ctx.input.key_repeat = enable;
+            Ok(())
+        })
+    })?;
+    keyboard_table.set("setKeyRepeat", set_key_repeat_func)?;
+    let has_key_repeat_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| Ok(ctx.input.key_repeat))
+    })?;
+    keyboard_table.set("hasKeyRepeat", has_key_repeat_func)?;
+    let set_text_input_func = lua.create_function(|lua, enable: bool| {
+        with_context(lua, |ctx| {
+            if let Some(graphics) = ctx.graphics.as_ref() {
+                let video = graphics.surface.sdl().video().map_err(mlua::Error::RuntimeError)?;
+                if enable {
+                    video.text_input().start();
+                } else {
+                    video.text_input().stop();
+                }
+            }
+            Ok(())
+        })
+    })?;
+    keyboard_table.set("setTextInput", set_text_input_func)?;
+    let has_text_input_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| match ctx.graphics.as_ref() {
+            Some(graphics) => {
+                let video = graphics.surface.sdl().video().map_err(mlua::Error::RuntimeError)?;
+                Ok(video.text_input().is_active())
+            },
+            None => Ok(false),
+        })
+    })?;
+    keyboard_table.set("hasTextInput", has_text_input_func)?;
+
+    let globals = lua.globals();
+    let rock_table: Table = globals.get("rock")?;
+    rock_table.set("keyboard", keyboard_table)?;
+    Ok(())
+}
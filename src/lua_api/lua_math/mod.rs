@@ -1,12 +1,160 @@
 use std::sync::Arc;
 use std::convert::AsMut;
 
-use mlua::{UserData, MetaMethod, Result, UserDataMethods, Table, Value, ToLua};
+use mlua::{UserData, MetaMethod, Result, UserDataMethods, Table, Value, ToLua, FromLua, Function, Lua};
 use glam::*;
 
 use crate::math::Transform;
 use super::LuaApi;
 
+/// One side of a vector's arithmetic metamethod, which can be a plain number for scalar math.
+enum Operand<V> {
+    Scalar(f32),
+    Vector(V),
+}
+
+impl<'lua, V: FromLua<'lua>> FromLua<'lua> for Operand<V> {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> Result<Self> {
+        match value {
+            Value::Integer(_) | Value::Number(_) => Ok(Operand::Scalar(f32::from_lua(value, lua)?)),
+            value => Ok(Operand::Vector(V::from_lua(value, lua)?)),
+        }
+    }
+}
+
+fn vector_string(name: &str, components: &[f32]) -> String {
+    let components: Vec<String> = components.iter().map(|c| c.to_string()).collect();
+    format!("{}({})", name, components.join(", "))
+}
+
+/// `tostring(value)`, so vectors can be concatenated with anything Lua can print.
+fn lua_tostring<'lua>(lua: &'lua Lua, value: Value<'lua>) -> Result<String> {
+    let tostring: Function = lua.globals().get("tostring")?;
+    tostring.call(value)
+}
+
+/// Adds the parts of the vector API that are the same for every vector type:
+/// scalar arithmetic, field access, conversions and the common vector math.
+macro_rules! add_vector_methods {
+    ($methods:ident, $lua_type:ident, $name:expr, $($field:ident),+) => {
+        $methods.add_meta_function(MetaMethod::ToString, |_, obj: $lua_type| {
+            Ok(vector_string($name, &[$(obj.vec.$field),+]))
+        });
+
+        $methods.add_meta_function(MetaMethod::Concat, |lua, (a, b): (Value, Value)| {
+            Ok(lua_tostring(lua, a)? + &lua_tostring(lua, b)?)
+        });
+
+        //Amount of components
+        $methods.add_meta_function(MetaMethod::Len, |_, _: $lua_type| {
+            Ok([$(stringify!($field)),+].len())
+        });
+
+        //`v.x`, methods are looked up before this gets called
+        $methods.add_meta_method(MetaMethod::Index, |_, obj, key: String| {
+            match key.as_str() {
+                $(stringify!($field) => Ok(Some(obj.vec.$field)),)+
+                _ => Ok(None),
+            }
+        });
+
+        $methods.add_meta_method_mut(MetaMethod::NewIndex, |_, obj, (key, value): (String, f32)| {
+            let vec = Arc::make_mut(&mut obj.vec);
+            match key.as_str() {
+                $(stringify!($field) => vec.$field = value,)+
+                _ => return Err(mlua::Error::RuntimeError(format!("Vectors have no field `{}`", key))),
+            }
+            Ok(())
+        });
+
+        //Componentwise with another vector, or scaled by a number on either side
+        $methods.add_meta_function(MetaMethod::Mul, |_, (a, b): (Operand<$lua_type>, Operand<$lua_type>)| {
+            let vec = match (a, b) {
+                (Operand::Vector(a), Operand::Vector(b)) => *a.vec * *b.vec,
+                (Operand::Vector(v), Operand::Scalar(s)) | (Operand::Scalar(s), Operand::Vector(v)) => *v.vec * s,
+                (Operand::Scalar(_), Operand::Scalar(_)) => return Err(mlua::Error::RuntimeError("Expected a vector".to_owned())),
+            };
+            Ok($lua_type { vec: Arc::new(vec) })
+        });
+
+        $methods.add_meta_function(MetaMethod::Div, |_, (a, b): (Operand<$lua_type>, Operand<$lua_type>)| {
+            let vec = match (a, b) {
+                (Operand::Vector(a), Operand::Vector(b)) => *a.vec / *b.vec,
+                (Operand::Vector(v), Operand::Scalar(s)) => *v.vec / s,
+                (Operand::Scalar(s), Operand::Vector(v)) => s / *v.vec,
+                (Operand::Scalar(_), Operand::Scalar(_)) => return Err(mlua::Error::RuntimeError("Expected a vector".to_owned())),
+            };
+            Ok($lua_type { vec: Arc::new(vec) })
+        });
+
+        $methods.add_method("unpack", |_, obj, ()| {
+            Ok(($(obj.vec.$field),+))
+        });
+
+        $methods.add_method("dot", |_, obj, other: $lua_type| {
+            Ok(obj.vec.dot(*other.vec))
+        });
+
+        $methods.add_method("length", |_, obj, ()| {
+            Ok(obj.vec.length())
+        });
+
+        $methods.add_method("lengthSquared", |_, obj, ()| {
+            Ok(obj.vec.length_squared())
+        });
+
+        //A zero vector stays zero instead of becoming NaN
+        $methods.add_method("normalize", |_, obj, ()| {
+            let length = obj.vec.length();
+            let vec = if length > 0.0 { *obj.vec / length } else { *obj.vec };
+            Ok($lua_type { vec: Arc::new(vec) })
+        });
+
+        $methods.add_method("distance", |_, obj, other: $lua_type| {
+            Ok(obj.vec.distance(*other.vec))
+        });
+
+        $methods.add_method("lerp", |_, obj, (other, t): ($lua_type, f32)| {
+            Ok($lua_type { vec: Arc::new(obj.vec.lerp(*other.vec, t)) })
+        });
+
+        $methods.add_method("min", |_, obj, other: $lua_type| {
+            Ok($lua_type { vec: Arc::new(obj.vec.min(*other.vec)) })
+        });
+
+        $methods.add_method("max", |_, obj, other: $lua_type| {
+            Ok($lua_type { vec: Arc::new(obj.vec.max(*other.vec)) })
+        });
+
+        $methods.add_method("clamp", |_, obj, (min, max): ($lua_type, $lua_type)| {
+            Ok($lua_type { vec: Arc::new(obj.vec.max(*min.vec).min(*max.vec)) })
+        });
+
+        $methods.add_method("abs", |_, obj, ()| {
+            Ok($lua_type { vec: Arc::new(obj.vec.abs()) })
+        });
+
+        $methods.add_method("floor", |_, obj, ()| {
+            Ok($lua_type { vec: Arc::new(obj.vec.floor()) })
+        });
+
+        //Mirrors the vector off a surface with the given (normalized) normal
+        $methods.add_method("reflect", |_, obj, normal: $lua_type| {
+            let vec = *obj.vec - *normal.vec * (2.0 * obj.vec.dot(*normal.vec));
+            Ok($lua_type { vec: Arc::new(vec) })
+        });
+
+        //In radians, 0 if either vector is zero
+        $methods.add_method("angleBetween", |_, obj, other: $lua_type| {
+            let lengths = obj.vec.length() * other.vec.length();
+            if lengths == 0.0 {
+                return Ok(0.0);
+            }
+            Ok((obj.vec.dot(*other.vec) / lengths).max(-1.0).min(1.0).acos())
+        });
+    };
+}
+
 ///Loads rock.math
 pub fn load_math_table(lua: &LuaApi) -> Result<()> {
     let math_table = lua.create_table()?;
@@ -19,11 +167,31 @@ pub fn load_math_table(lua: &LuaApi) -> Result<()> {
         Ok(vec3_constructor(x,y,z))
     })?;
     math_table.set("vec3", vec3_func)?;
+    let vec4_func = lua.create_function(|_,(x,y,z,w)| {
+        Ok(vec4_constructor(x,y,z,w))
+    })?;
+    math_table.set("vec4", vec4_func)?;
     let quat_euler_func = lua.create_function(|_,(yaw,pitch,roll)| {
         let quat = quat_euler_constructor(yaw, pitch, roll);
         Ok(quat)
     })?;
     math_table.set("quat_euler", quat_euler_func)?;
+    let quat_axis_angle_func = lua.create_function(|_,(axis,angle)| {
+        Ok(quat_axis_angle_constructor(axis, angle))
+    })?;
+    math_table.set("quat_axis_angle", quat_axis_angle_func)?;
+    let quat_identity_func = lua.create_function(|_,()| {
+        Ok(LuaQuat { quat: Arc::new(Quat::identity()) })
+    })?;
+    math_table.set("quat_identity", quat_identity_func)?;
+    let quat_from_to_func = lua.create_function(|_,(from,to)| {
+        Ok(quat_from_to_constructor(from, to))
+    })?;
+    math_table.set("quat_from_to", quat_from_to_func)?;
+    let quat_look_rotation_func = lua.create_function(|_,(forward,up)| {
+        Ok(quat_look_rotation_constructor(forward, up))
+    })?;
+    math_table.set("quat_look_rotation", quat_look_rotation_func)?;
     let transform_func = lua.create_function(|_,(pos,rot,scale)| {
         Ok(transform_constructor(pos, rot, scale))
     })?;
@@ -42,9 +210,7 @@ pub struct LuaVec2 {
 
 impl UserData for LuaVec2 {
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
-        methods.add_meta_function(MetaMethod::ToString, |_, obj: Self| {
-            Ok(format!("Vector {{ vec: {} }}", obj.vec))
-        });
+        add_vector_methods!(methods, LuaVec2, "vec2", x, y);
 
         // Comparisons
         methods.add_meta_function(MetaMethod::Eq, |_, (a,b): (LuaVec2, LuaVec2)| {
@@ -68,14 +234,6 @@ impl UserData for LuaVec2 {
             Ok(LuaVec2 { vec: Arc::new(*a.vec - *b.vec) })
         });
 
-        methods.add_meta_function(MetaMethod::Mul, |_, (a,b): (LuaVec2, LuaVec2)| {
-            Ok(LuaVec2 { vec: Arc::new(*a.vec * *b.vec) })
-        });
-
-        methods.add_meta_function(MetaMethod::Div, |_, (a,b): (LuaVec2, LuaVec2)| {
-            Ok(LuaVec2 { vec: Arc::new(*a.vec / *b.vec) })
-        });
-
         methods.add_meta_function(MetaMethod::Pow, |_, (a,b): (LuaVec2, f32)| {
             Ok(LuaVec2 { vec: Arc::new(a.vec.powf(b)) })
         });
@@ -102,6 +260,11 @@ impl UserData for LuaVec2 {
             Arc::make_mut(&mut obj.vec).y = y;
             Ok(())
         });
+
+        //The z of the 3D cross product, positive when `other` is counter-clockwise from this vector
+        methods.add_method("cross", |_, obj, other: LuaVec2| {
+            Ok(obj.vec.perp_dot(*other.vec))
+        });
     }
 }
 
@@ -118,9 +281,7 @@ pub struct LuaVec3 {
 
 impl UserData for LuaVec3 {
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
-        methods.add_meta_function(MetaMethod::ToString, |_, obj: Self| {
-            Ok(format!("Vector {{ vec: {} }}", obj.vec))
-        });
+        add_vector_methods!(methods, LuaVec3, "vec3", x, y, z);
 
         // Comparisons
         methods.add_meta_function(MetaMethod::Eq, |_, (a,b): (LuaVec3, LuaVec3)| {
@@ -144,14 +305,6 @@ impl UserData for LuaVec3 {
             Ok(LuaVec3 { vec: Arc::new(*a.vec - *b.vec) })
         });
 
-        methods.add_meta_function(MetaMethod::Mul, |_, (a,b): (LuaVec3, LuaVec3)| {
-            Ok(LuaVec3 { vec: Arc::new(*a.vec * *b.vec) })
-        });
-
-        methods.add_meta_function(MetaMethod::Div, |_, (a,b): (LuaVec3, LuaVec3)| {
-            Ok(LuaVec3 { vec: Arc::new(*a.vec / *b.vec) })
-        });
-
         methods.add_meta_function(MetaMethod::Pow, |_, (a,b): (LuaVec3, f32)| {
             Ok(LuaVec3 { vec: Arc::new(a.vec.powf(b)) })
         });
@@ -187,6 +340,10 @@ impl UserData for LuaVec3 {
             Arc::make_mut(&mut obj.vec).z = z;
             Ok(())
         });
+
+        methods.add_method("cross", |_, obj, other: LuaVec3| {
+            Ok(LuaVec3 { vec: Arc::new(obj.vec.cross(*other.vec)) })
+        });
     }
 }
 
@@ -203,9 +360,7 @@ pub struct LuaVec4 {
 
 impl UserData for LuaVec4 {
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
-        methods.add_meta_function(MetaMethod::ToString, |_, obj: Self| {
-            Ok(format!("Vector {{ vec: {} }}", obj.vec))
-        });
+        add_vector_methods!(methods, LuaVec4, "vec4", x, y, z, w);
 
         // Comparisons
         methods.add_meta_function(MetaMethod::Eq, |_, (a,b): (LuaVec4, LuaVec4)| {
@@ -229,14 +384,6 @@ impl UserData for LuaVec4 {
             Ok(LuaVec4 { vec: Arc::new(*a.vec - *b.vec) })
         });
 
-        methods.add_meta_function(MetaMethod::Mul, |_, (a,b): (LuaVec4, LuaVec4)| {
-            Ok(LuaVec4 { vec: Arc::new(*a.vec * *b.vec) })
-        });
-
-        methods.add_meta_function(MetaMethod::Div, |_, (a,b): (LuaVec4, LuaVec4)| {
-            Ok(LuaVec4 { vec: Arc::new(*a.vec / *b.vec) })
-        });
-
         methods.add_meta_function(MetaMethod::Pow, |_, (a,b): (LuaVec4, f32)| {
             Ok(LuaVec4 { vec: Arc::new(a.vec.powf(b)) })
         });
@@ -258,6 +405,10 @@ impl UserData for LuaVec4 {
             Ok(obj.vec.z)
         });
 
+        methods.add_method("getW", |_, obj, ()| {
+            Ok(obj.vec.w)
+        });
+
         methods.add_method_mut("setX", |_, obj, x: f32| {
             Arc::make_mut(&mut obj.vec).x = x;
             Ok(())
@@ -272,6 +423,11 @@ impl UserData for LuaVec4 {
             Arc::make_mut(&mut obj.vec).z = z;
             Ok(())
         });
+
+        methods.add_method_mut("setW", |_, obj, w: f32| {
+            Arc::make_mut(&mut obj.vec).w = w;
+            Ok(())
+        });
     }
 }
 
@@ -289,7 +445,7 @@ pub struct LuaQuat {
 impl UserData for LuaQuat {
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_meta_function(MetaMethod::ToString, |_, obj: Self| {
-            Ok(format!("Transform {{ quat: {} }}", obj.quat))
+            Ok(vector_string("quat", &[obj.quat.x, obj.quat.y, obj.quat.z, obj.quat.w]))
         });
 
         methods.add_meta_function(MetaMethod::Eq, |_, (a,b): (LuaQuat, LuaQuat)| {
@@ -312,8 +468,15 @@ impl UserData for LuaQuat {
             Ok(LuaQuat { quat: Arc::new(*a.quat - *b.quat) })
         });
 
-        methods.add_meta_function(MetaMethod::Mul, |_, (a,b): (LuaQuat, LuaQuat)| {
-            Ok(LuaQuat { quat: Arc::new(*a.quat * *b.quat) })
+        //Combines two rotations, or rotates a vec3
+        methods.add_meta_function(MetaMethod::Mul, |lua, (a,b): (LuaQuat, Value)| {
+            if let Value::UserData(ud) = &b {
+                if let Ok(vec) = ud.borrow::<LuaVec3>() {
+                    return LuaVec3 { vec: Arc::new(a.quat.mul_vec3(*vec.vec)) }.to_lua(lua);
+                }
+            }
+            let b = LuaQuat::from_lua(b, lua)?;
+            LuaQuat { quat: Arc::new(*a.quat * *b.quat) }.to_lua(lua)
         });
 
         methods.add_meta_function(MetaMethod::Div, |_, (a,b): (LuaQuat, f32)| {
@@ -323,6 +486,46 @@ impl UserData for LuaQuat {
         methods.add_meta_function(MetaMethod::Unm, |_, a: LuaQuat| {
             Ok(LuaQuat { quat: Arc::new(-*a.quat) })
         });
+
+        //Takes the shortest way around
+        methods.add_method("slerp", |_, obj, (other, t): (LuaQuat, f32)| {
+            let end = if obj.quat.dot(*other.quat) < 0.0 { -*other.quat } else { *other.quat };
+            Ok(LuaQuat { quat: Arc::new(obj.quat.slerp(end, t)) })
+        });
+
+        //Cheaper than slerp, but doesn't rotate at a constant speed
+        methods.add_method("nlerp", |_, obj, (other, t): (LuaQuat, f32)| {
+            Ok(LuaQuat { quat: Arc::new(obj.quat.lerp(*other.quat, t)) })
+        });
+
+        methods.add_method("inverse", |_, obj, ()| {
+            let length_squared = obj.quat.length_squared();
+            let quat = if length_squared > 0.0 { obj.quat.conjugate() / length_squared } else { *obj.quat };
+            Ok(LuaQuat { quat: Arc::new(quat) })
+        });
+
+        methods.add_method("conjugate", |_, obj, ()| {
+            Ok(LuaQuat { quat: Arc::new(obj.quat.conjugate()) })
+        });
+
+        methods.add_method("normalize", |_, obj, ()| {
+            let quat = if obj.quat.length_squared() > 0.0 { obj.quat.normalize() } else { Quat::identity() };
+            Ok(LuaQuat { quat: Arc::new(quat) })
+        });
+
+        //Same order as `rock.math.quat_euler`
+        methods.add_method("toEuler", |_, obj, ()| {
+            Ok(quat_to_ypr(*obj.quat))
+        });
+
+        methods.add_method("toAxisAngle", |_, obj, ()| {
+            let (axis, angle) = obj.quat.to_axis_angle();
+            Ok((LuaVec3 { vec: Arc::new(axis) }, angle))
+        });
+
+        methods.add_method("dot", |_, obj, other: LuaQuat| {
+            Ok(obj.quat.dot(*other.quat))
+        });
     }
 }
 
@@ -332,6 +535,85 @@ pub fn quat_euler_constructor(yaw: f32, pitch: f32, roll: f32) -> LuaQuat {
     }
 }
 
+/// Rotation of `angle` radians around `axis`, which doesn't need to be normalized.
+pub fn quat_axis_angle_constructor(axis: LuaVec3, angle: f32) -> LuaQuat {
+    let quat = if axis.vec.length_squared() > 0.0 {
+        Quat::from_axis_angle(axis.vec.normalize(), angle)
+    } else {
+        Quat::identity()
+    };
+    LuaQuat {
+        quat: Arc::new(quat)
+    }
+}
+
+/// Shortest rotation that turns the direction `from` into `to`.
+pub fn quat_from_to_constructor(from: LuaVec3, to: LuaVec3) -> LuaQuat {
+    LuaQuat {
+        quat: Arc::new(quat_from_to(*from.vec, *to.vec))
+    }
+}
+
+/// Rotation that makes +Z face `forward`, with +Y as close to `up` (default +Y) as possible.
+pub fn quat_look_rotation_constructor(forward: LuaVec3, up: Option<LuaVec3>) -> LuaQuat {
+    let up = up.map_or_else(Vec3::unit_y, |up| *up.vec);
+    LuaQuat {
+        quat: Arc::new(quat_look_rotation(*forward.vec, up))
+    }
+}
+
+fn quat_from_to(from: Vec3, to: Vec3) -> Quat {
+    if from.length_squared() == 0.0 || to.length_squared() == 0.0 {
+        return Quat::identity();
+    }
+    let (from, to) = (from.normalize(), to.normalize());
+    let dot = from.dot(to);
+    if dot > 1.0 - 1e-6 {
+        Quat::identity()
+    } else if dot < -1.0 + 1e-6 {
+        //Opposite directions, so any axis perpendicular to them works
+        let other = if from.x.abs() < 0.9 { Vec3::unit_x() } else { Vec3::unit_y() };
+        Quat::from_axis_angle(other.cross(from).normalize(), std::f32::consts::PI)
+    } else {
+        let axis = from.cross(to);
+        Quat::from_xyzw(axis.x, axis.y, axis.z, 1.0 + dot).normalize()
+    }
+}
+
+fn quat_look_rotation(forward: Vec3, up: Vec3) -> Quat {
+    if forward.length_squared() == 0.0 {
+        return Quat::identity();
+    }
+    let z_axis = forward.normalize();
+    let mut x_axis = up.cross(z_axis);
+    //Looking straight along `up`, so any sideways axis will do
+    if x_axis.length_squared() < 1e-12 {
+        x_axis = quat_from_to(Vec3::unit_z(), z_axis).mul_vec3(Vec3::unit_x());
+    }
+    let x_axis = x_axis.normalize();
+    let y_axis = z_axis.cross(x_axis);
+    Quat::from_rotation_mat3(&Mat3::from_cols(x_axis, y_axis, z_axis))
+}
+
+/// Inverse of `Quat::from_rotation_ypr`, which rotates around Z, then X, then Y.
+fn quat_to_ypr(quat: Quat) -> (f32, f32, f32) {
+    let (x, y, z, w) = (quat.x, quat.y, quat.z, quat.w);
+    let m12 = 2.0 * (y * z - x * w);
+    let m02 = 2.0 * (x * z + y * w);
+    let m22 = 1.0 - 2.0 * (x * x + y * y);
+    //Not `asin(-m12)`, which loses most of its precision close to straight up or down
+    let pitch = (-m12).atan2((m02 * m02 + m22 * m22).sqrt());
+    if m12.abs() < 0.999_999 {
+        let yaw = m02.atan2(m22);
+        let roll = (2.0 * (x * y + z * w)).atan2(1.0 - 2.0 * (x * x + z * z));
+        (yaw, pitch, roll)
+    } else {
+        //Gimbal lock, where yaw and roll rotate around the same axis
+        let yaw = (-2.0 * (x * z - y * w)).atan2(1.0 - 2.0 * (y * y + z * z));
+        (yaw, pitch, 0.0)
+    }
+}
+
 #[derive(Clone)]
 pub struct LuaTransform {
     pub transform: Arc<Transform>,
@@ -380,3 +662,77 @@ pub fn transform_constructor(pos: LuaVec3, rot: LuaQuat, scale: LuaVec3) -> LuaT
         transform: Arc::new(transform)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    fn assert_vec3_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-4, "{} != {}", a, b);
+    }
+
+    /// Whether two quaternions rotate the same way, `q` and `-q` do.
+    fn assert_same_rotation(a: Quat, b: Quat) {
+        assert_near(a.dot(b).abs(), 1.0);
+    }
+
+    #[test]
+    fn quat_to_ypr_inverts_from_rotation_ypr() {
+        for &(yaw, pitch, roll) in &[(0.0, 0.0, 0.0), (0.3, -0.7, 1.2), (-2.5, 1.1, -0.4), (1.0, 0.2, 3.0), (3.0, -1.5, -3.0)] {
+            let (y, p, r) = quat_to_ypr(Quat::from_rotation_ypr(yaw, pitch, roll));
+            assert_near(y, yaw);
+            assert_near(p, pitch);
+            assert_near(r, roll);
+        }
+    }
+
+    #[test]
+    fn quat_to_ypr_handles_gimbal_lock() {
+        for &pitch in &[FRAC_PI_2, -FRAC_PI_2] {
+            //Without roll, the angles come back exactly
+            let (y, p, r) = quat_to_ypr(Quat::from_rotation_ypr(0.4, pitch, 0.0));
+            assert_near(y, 0.4);
+            assert_near(p, pitch);
+            assert_near(r, 0.0);
+
+            //With roll, yaw absorbs it, but the rotation stays the same
+            let quat = Quat::from_rotation_ypr(0.4, pitch, 0.7);
+            let (y, p, r) = quat_to_ypr(quat);
+            assert_near(p, pitch);
+            assert_same_rotation(Quat::from_rotation_ypr(y, p, r), quat);
+        }
+    }
+
+    #[test]
+    fn quat_from_to_rotates_from_onto_to() {
+        let dirs = [Vec3::unit_x(), Vec3::unit_y(), -Vec3::unit_z(), Vec3::new(0.95, 0.1, -0.3), Vec3::new(-2.0, 3.0, 0.5)];
+        for &from in &dirs {
+            for &to in &dirs {
+                assert_vec3_near(quat_from_to(from, to).mul_vec3(from.normalize()), to.normalize());
+            }
+            //Opposite directions have no single shortest rotation
+            assert_vec3_near(quat_from_to(from, -from).mul_vec3(from), -from);
+        }
+        assert_same_rotation(quat_from_to(Vec3::zero(), Vec3::unit_x()), Quat::identity());
+    }
+
+    #[test]
+    fn quat_look_rotation_faces_forward() {
+        let forwards = [Vec3::unit_z(), -Vec3::unit_z(), Vec3::new(1.0, 2.0, -3.0), Vec3::new(0.0, 0.0, 5.0), Vec3::unit_y(), -Vec3::unit_y()];
+        for &forward in &forwards {
+            let quat = quat_look_rotation(forward, Vec3::unit_y());
+            assert_vec3_near(quat.mul_vec3(Vec3::unit_z()), forward.normalize());
+            //Up stays perpendicular to forward, and points up as far as it can
+            let up = quat.mul_vec3(Vec3::unit_y());
+            assert_near(up.dot(forward.normalize()), 0.0);
+            assert!(up.dot(Vec3::unit_y()) >= -1e-4);
+        }
+        assert_same_rotation(quat_look_rotation(Vec3::unit_z(), Vec3::unit_y()), Quat::identity());
+        assert_same_rotation(quat_look_rotation(Vec3::unit_x(), Vec3::unit_y()), Quat::from_rotation_y(PI / 2.0));
+    }
+}
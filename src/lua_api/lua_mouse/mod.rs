@@ -0,0 +1,106 @@
+use mlua::{Lua, Table, Result, Variadic};
+
+use sdl2::mouse::{Cursor, SystemCursor};
+use sdl2::sys::SDL_bool;
+
+use super::{LuaApi, with_context};
+use crate::events::mouse_button_from_index;
+use crate::graphics::Graphics;
+
+/// Runs `func` on the window, or returns `default` when running headless.
+fn with_graphics<R, F>(lua: &Lua, default: R, func: F) -> Result<R>
+where
+    F: FnOnce(&mut Graphics) -> Result<R>
+{
+    with_context(lua, |ctx| match ctx.graphics.as_mut() {
+        Some(graphics) => func(graphics),
+        None => Ok(default),
+    })
+}
+
+/// Cursor names are the same as in Love2D.
+fn system_cursor(name: &str) -> Result<SystemCursor> {
+    Ok(match name {
+        "arrow" => SystemCursor::Arrow,
+        "ibeam" => SystemCursor::IBeam,
+        "wait" => SystemCursor::Wait,
+        "waitarrow" => SystemCursor::WaitArrow,
+        "crosshair" => SystemCursor::Crosshair,
+        "sizenwse" => SystemCursor::SizeNWSE,
+        "sizenesw" => SystemCursor::SizeNESW,
+        "sizewe" => SystemCursor::SizeWE,
+        "sizens" => SystemCursor::SizeNS,
+        "sizeall" => SystemCursor::SizeAll,
+        "no" => SystemCursor::No,
+        "hand" => SystemCursor::Hand,
+        _ => return Err(mlua::Error::RuntimeError(format!("Invalid system cursor type: {}", name))),
+    })
+}
+
+///Loads rock.mouse
+pub fn load_mouse_table(lua: &LuaApi) -> Result<()> {
+    let mouse_table = lua.create_table()?;
+
+    //Position and buttons come from the events the game got, so they are the same during replays and when headless
+    let get_position_func = lua.create_function(|lua, ()| {
+        with_context(lua, |ctx| Ok(ctx.input.mouse_position()))
+    })?;
+    mouse_table.set("getPosition", get_position_func)?;
+    let is_down_func = lua.create_function(|lua, buttons: Variadic<u8>| {
+        for index in buttons.iter() {
+            mouse_button_from_index(*index).ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid mouse button: {}", index)))?;
+        }
+        with_context(lua, |ctx| Ok(buttons.iter().any(|button| ctx.input.is_mouse_down(*button))))
+    })?;
+    mouse_table.set("isDown", is_down_func)?;
+    let set_relative_mode_func = lua.create_function(|lua, enable: bool| {
+        with_graphics(lua, (), |graphics| {
+            graphics.surface.sdl().mouse().set_relative_mouse_mode(enable);
+            Ok(())
+        })
+    })?;
+    mouse_table.set("setRelativeMode", set_relative_mode_func)?;
+    let get_relative_mode_func = lua.create_function(|lua, ()| {
+        with_graphics(lua, false, |graphics| Ok(graphics.surface.sdl().mouse().relative_mouse_mode()))
+    })?;
+    mouse_table.set("getRelativeMode", get_relative_mode_func)?;
+    let set_grabbed_func = lua.create_function(|lua, grab: bool| {
+        with_graphics(lua, (), |graphics| {
+            let grab = if grab { SDL_bool::SDL_TRUE } else { SDL_bool::SDL_FALSE };
+            unsafe { sdl2::sys::SDL_SetWindowGrab(graphics.surface.window().raw(), grab); }
+            Ok(())
+        })
+    })?;
+    mouse_table.set("setGrabbed", set_grabbed_func)?;
+    let is_grabbed_func = lua.create_function(|lua, ()| {
+        with_graphics(lua, false, |graphics| Ok(graphics.surface.window().grab()))
+    })?;
+    mouse_table.set("isGrabbed", is_grabbed_func)?;
+    let set_visible_func = lua.create_function(|lua, visible: bool| {
+        with_graphics(lua, (), |graphics| {
+            graphics.surface.sdl().mouse().show_cursor(visible);
+            Ok(())
+        })
+    })?;
+    mouse_table.set("setVisible", set_visible_func)?;
+    let is_visible_func = lua.create_function(|lua, ()| {
+        with_graphics(lua, false, |graphics| Ok(graphics.surface.sdl().mouse().is_cursor_showing()))
+    })?;
+    mouse_table.set("isVisible", is_visible_func)?;
+    //`setCursor()` without a name goes back to the default cursor
+    let set_cursor_func = lua.create_function(|lua, name: Option<String>| {
+        let cursor = system_cursor(name.as_deref().unwrap_or("arrow"))?;
+        with_graphics(lua, (), |graphics| {
+            let cursor = Cursor::from_system(cursor).map_err(mlua::Error::RuntimeError)?;
+            cursor.set();
+            graphics.cursor = Some(cursor);
+            Ok(())
+        })
+    })?;
+    mouse_table.set("setCursor", set_cursor_func)?;
+
+    let globals = lua.globals();
+    let rock_table: Table = globals.get("rock")?;
+    rock_table.set("mouse", mouse_table)?;
+    Ok(())
+}
@@ -1,9 +1,28 @@
 use std::ops::{Deref, DerefMut};
 
-use mlua::{Chunk, Function, Table, Lua, prelude::ToLua, MetaMethod, Result, UserData, UserDataMethods, Variadic};
+use mlua::{AnyUserData, Chunk, Function, Table, Lua, StdLib, Value, prelude::ToLua, ToLuaMulti, FromLuaMulti, MetaMethod, Result, UserData, UserDataMethods, Variadic};
 
 pub mod lua_graphics;
 pub mod lua_math;
+pub mod lua_filesystem;
+pub mod lua_keyboard;
+pub mod lua_mouse;
+pub mod lua_input;
+pub mod lua_joystick;
+
+use crate::config::ModulesConfig;
+use crate::context::{Context, EngineContext};
+use crate::vfs::{VirtualFileSystem, VfsPath};
+use crate::assets::AssetKind;
+use crate::error::RockError;
+
+/// Registry key of the engine context the bindings work on
+const CONTEXT: &str = "rock.context";
+
+/// Keeps the engine context in the Lua registry.
+struct ContextHandle(Context);
+
+impl UserData for ContextHandle {}
 
 pub struct LuaApi {
     lua: Lua,
@@ -22,24 +41,153 @@ impl DerefMut for LuaApi {
     }
 }
 
-/// Helper function that binds some custom functions
+/// Helper function that binds some custom functions.
+/// Only creates the `rock` table, modules are loaded
+/// with `load_modules` once `conf.lua` has been run.
 pub fn init_lua() -> LuaApi {
+    //No `io`, it works on the real file system instead of the VFS.
+    //No `debug` either, it would give games access to the registry (and the engine context in it)
     let lua = LuaApi {
-                lua: Lua::new(),
+                lua: Lua::new_with(StdLib::TABLE | StdLib::STRING | StdLib::BIT | StdLib::MATH | StdLib::OS | StdLib::PACKAGE),
             };
+    sandbox_globals(&lua).expect("Failed to remove unsafe functions!");
+    load_package_loader(&lua).expect("Failed to set up `require`!");
     load_main_table(&lua).expect("Failed to load `rock` table!");
-    lua_graphics::load_graphics_table(&lua).expect("Failed to load `rock.graphics` table!");
-    lua_math::load_math_table(&lua).expect("Failed to load `rock.math` table!");
     lua
 }
 
+/// Removes the functions that touch the real file system or the process,
+/// leaving the rest of `os` (like `os.time` and `os.clock`) to the game.
+fn sandbox_globals(lua: &LuaApi) -> Result<()> {
+    let globals = lua.globals();
+    for name in &["dofile", "loadfile", "io"] {
+        globals.set(*name, Value::Nil)?;
+    }
+    let os_table: Table = globals.get("os")?;
+    for name in &["execute", "remove", "rename", "exit", "getenv", "tmpname"] {
+        os_table.set(*name, Value::Nil)?;
+    }
+    let package_table: Table = globals.get("package")?;
+    package_table.set("loadlib", Value::Nil)?;
+    Ok(())
+}
+
+/// Makes `require` look for modules in the game's files, through the VFS.
+/// `require("a.b")` loads `a/b.lua`, or `a/b/init.lua`.
+/// Only `package.preload` is kept from the default searchers, the others use the real file system.
+fn load_package_loader(lua: &LuaApi) -> Result<()> {
+    let package_table: Table = lua.globals().get("package")?;
+    let loaders: Table = package_table.get("loaders")?;
+    let preload_loader: Function = loaders.get(1)?;
+    let vfs_loader = lua.create_function(|lua, name: String| {
+        let base = name.replace('.', "/");
+        let mut not_found = String::new();
+        for path in &[format!("{}.lua", base), format!("{}/init.lua", base)] {
+            let path = VfsPath::new(path).map_err(|e| mlua::Error::from(RockError::Vfs(e)))?;
+            let mut bytes = Vec::new();
+            match with_context(lua, |ctx| Ok(ctx.vfs.read_bytes(path.as_str(), &mut bytes)))? {
+                Ok(()) => {
+                    let chunk = lua.load(&bytes).set_name(&format!("@{}", path.as_str()))?.into_function()?;
+                    //Not re-executed when it changes, but the game does hear about it
+                    with_context(lua, |ctx| {
+                        ctx.assets.watch(&ctx.vfs, path.as_str(), AssetKind::Other);
+                        Ok(())
+                    })?;
+                    return Ok(Value::Function(chunk));
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => not_found.push_str(&format!("\n\tno file '{}' in the game's files", path.as_str())),
+                Err(e) => return Err(RockError::Vfs(e).into()),
+            }
+        }
+        Ok(Value::String(lua.create_string(&not_found)?))
+    })?;
+    package_table.set("loaders", lua.create_sequence_from(vec![preload_loader, vfs_loader])?)?;
+    Ok(())
+}
+
+/// Loads the `rock.*` modules enabled in the game's configuration
+pub fn load_modules(lua: &LuaApi, modules: &ModulesConfig) {
+    if modules.graphics {
+        lua_graphics::load_graphics_table(&lua).expect("Failed to load `rock.graphics` table!");
+    }
+    if modules.math {
+        lua_math::load_math_table(&lua).expect("Failed to load `rock.math` table!");
+    }
+    if modules.filesystem {
+        lua_filesystem::load_filesystem_table(&lua).expect("Failed to load `rock.filesystem` table!");
+    }
+    if modules.keyboard {
+        lua_keyboard::load_keyboard_table(&lua).expect("Failed to load `rock.keyboard` table!");
+    }
+    if modules.mouse {
+        lua_mouse::load_mouse_table(&lua).expect("Failed to load `rock.mouse` table!");
+    }
+    if modules.input {
+        lua_input::load_input_table(&lua).expect("Failed to load `rock.input` table!");
+    }
+    if modules.joystick {
+        lua_joystick::load_joystick_table(&lua).expect("Failed to load `rock.joystick` table!");
+    }
+}
+
+/// Gives the bindings access to an engine context.
+pub fn set_context(lua: &LuaApi, ctx: Context) -> Result<()> {
+    lua.set_named_registry_value(CONTEXT, ContextHandle(ctx))
+}
+
+/// Returns the engine context, or an error when it hasn't been set yet
+/// (for example when Lua code calls into the engine while it's being configured).
+pub fn context(lua: &Lua) -> Result<Context> {
+    let handle: AnyUserData = lua.named_registry_value(CONTEXT)
+                                 .map_err(|_| mlua::Error::RuntimeError("The engine isn't running yet!".to_owned()))?;
+    let ctx = handle.borrow::<ContextHandle>()?.0.clone();
+    Ok(ctx)
+}
+
+/// Runs `func` with mutable access to the engine context.
+pub(crate) fn with_context<R, F>(lua: &Lua, func: F) -> Result<R>
+where
+    F: FnOnce(&mut EngineContext) -> Result<R>
+{
+    let ctx = context(lua)?;
+    let mut ctx = ctx.try_borrow_mut().map_err(|_| mlua::Error::RuntimeError("The engine is busy!".to_owned()))?;
+    func(&mut ctx)
+}
+
 /// Helper function that calls `lua.load(code)`
 /// Kind of useless at the moment
 pub fn load_code<'a>(lua: &'a LuaApi, code: &'a str) -> Chunk<'a, 'a> {
     lua.load(code)
 }
 
-pub fn call_rock_func<'a, A: ToLua<'a>>(lua: &'a LuaApi, func_name: &'a str, args: A) -> Result<()> {
+/// Same as `load_code`, but for source that isn't guaranteed to be valid UTF-8,
+/// like files read from the VFS.
+pub fn load_code_bytes<'a>(lua: &'a LuaApi, code: &'a [u8]) -> Chunk<'a, 'a> {
+    lua.load(code)
+}
+
+/// Re-executes game code in the live Lua state, then calls the optional `rock.reload()`.
+/// `rock.persistent` survives the reload, even when the code assigns a new table to it,
+/// so it can be used to keep the state of the current session.
+pub fn reload_code(lua: &LuaApi, name: &str, code: &[u8]) -> Result<()> {
+    let rock_table: Table = lua.globals().get("rock")?;
+    let persistent: Value = rock_table.get("persistent")?;
+    let result = lua.load(code).set_name(name).and_then(|chunk| chunk.exec());
+    if !matches!(persistent, Value::Nil) {
+        rock_table.set("persistent", persistent)?;
+    }
+    result?;
+    call_optional_rock_func(lua, "reload", ())
+}
+
+/// Seeds Lua's `math.random`, so games using it play out the same way every replay.
+pub fn seed_random(lua: &LuaApi, seed: u32) -> Result<()> {
+    let math_table: Table = lua.globals().get("math")?;
+    let randomseed: Function = math_table.get("randomseed")?;
+    randomseed.call(seed)
+}
+
+pub fn call_rock_func<'a, A: ToLuaMulti<'a>>(lua: &'a LuaApi, func_name: &'a str, args: A) -> Result<()> {
     { //Block to scope globals
         let globals = lua.globals();
         let rock_table: Table = globals.get("rock")?;
@@ -49,6 +197,49 @@ pub fn call_rock_func<'a, A: ToLua<'a>>(lua: &'a LuaApi, func_name: &'a str, arg
     Ok(())
 }
 
+/// Like `call_rock_func`, but does nothing if the function isn't defined.
+pub fn call_optional_rock_func<'a, A: ToLuaMulti<'a>>(lua: &'a LuaApi, func_name: &'a str, args: A) -> Result<()> {
+    { //Block to scope globals
+        let globals = lua.globals();
+        let rock_table: Table = globals.get("rock")?;
+        if let Some(func) = rock_table.get::<_, Option<Function>>(func_name)? {
+            func.call::<_, ()>(args)?;
+        }
+    }
+    Ok(())
+}
+
+/// Like `call_optional_rock_func`, but converts what the function returns.
+/// Returns `None` if the function isn't defined.
+pub fn call_optional_rock_func_returning<'a, A: ToLuaMulti<'a>, R: FromLuaMulti<'a>>(lua: &'a LuaApi, func_name: &'a str, args: A) -> Result<Option<R>> {
+    let globals = lua.globals();
+    let rock_table: Table = globals.get("rock")?;
+    match rock_table.get::<_, Option<Function>>(func_name)? {
+        Some(func) => Ok(Some(func.call(args)?)),
+        None => Ok(None),
+    }
+}
+
+/// Turns an error into the message shown on the error screen, including its traceback.
+pub fn error_message(e: &mlua::Error) -> String {
+    match e {
+        mlua::Error::CallbackError { traceback, cause } => format!("{}\n{}", error_message(cause), traceback),
+        mlua::Error::RuntimeError(msg) => msg.clone(),
+        e => e.to_string(),
+    }
+}
+
+/// Calls `rock.errorhandler(msg)` if the game defines it.
+/// If it returns a string, that gets shown on the error screen instead of `msg`.
+pub fn call_error_handler(lua: &LuaApi, msg: &str) -> Result<Option<String>> {
+    let rock_table: Table = lua.globals().get("rock")?;
+    let handler: Option<Function> = rock_table.get("errorhandler")?;
+    match handler {
+        Some(handler) => handler.call(msg),
+        None => Ok(None),
+    }
+}
+
 fn load_main_table<'a>(lua: &'a LuaApi) -> Result<()> {
     let rock_table = lua.create_table()?;
     let load_func = lua.create_function(|_,()| {
@@ -59,6 +250,10 @@ fn load_main_table<'a>(lua: &'a LuaApi) -> Result<()> {
         Ok(())
     })?;
     rock_table.set("update", update_func)?;
+    let fixed_update_func = lua.create_function(|_,_step: f32| {
+        Ok(())
+    })?;
+    rock_table.set("fixedupdate", fixed_update_func)?;
     let draw_func = lua.create_function(|_,()| {
         Ok(())
     })?;
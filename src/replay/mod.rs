@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Serialize, Deserialize};
+
+use crate::events::RockEvent;
+use crate::vfs::VirtualFileSystem;
+use crate::error::{RockError, Result};
+
+/// Bumped whenever the format changes, as older recordings can't be replayed anymore.
+const REPLAY_VERSION: u32 = 1;
+
+/// Written once at the start of a recording.
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    /// What Lua's `math.random` got seeded with
+    seed: u32,
+}
+
+/// One frame of a recording: the events the game got before it, and its delta time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub events: Vec<RockEvent>,
+    pub dt: f32,
+}
+
+/// A seed for the game's randomness when recording, different every run.
+pub fn random_seed() -> u32 {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    time.as_secs() as u32 ^ time.subsec_nanos()
+}
+
+/// Records every event and frame to a file (in the save directory).
+/// Frames are appended as they happen, so the recording survives the game crashing.
+pub struct Recorder {
+    path: String,
+    events: Vec<RockEvent>,
+}
+
+impl Recorder {
+    /// Starts a new recording at `path`, replacing any recording already there.
+    pub fn create(vfs: &dyn VirtualFileSystem, path: &str, seed: u32) -> Result<Self> {
+        let header = ReplayHeader {
+            version: REPLAY_VERSION,
+            seed: seed,
+        };
+        let bytes = bincode::serialize(&header).map_err(|e| RockError::Asset(format!("Failed to encode replay: {}", e)))?;
+        vfs.write_bytes(path, &bytes)?;
+        Ok(Self {
+            path: path.to_owned(),
+            events: Vec::new(),
+        })
+    }
+
+    pub fn record_event(&mut self, event: &RockEvent) {
+        self.events.push(event.clone());
+    }
+
+    /// Writes a frame, along with the events recorded since the last one.
+    pub fn record_frame(&mut self, vfs: &dyn VirtualFileSystem, dt: f32) -> Result<()> {
+        let frame = ReplayFrame {
+            events: std::mem::take(&mut self.events),
+            dt: dt,
+        };
+        let bytes = bincode::serialize(&frame).map_err(|e| RockError::Asset(format!("Failed to encode replay: {}", e)))?;
+        vfs.append_bytes(&self.path, &bytes)?;
+        Ok(())
+    }
+}
+
+/// A recording being played back, frame by frame.
+pub struct Replay {
+    pub seed: u32,
+    frames: VecDeque<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn load(vfs: &dyn VirtualFileSystem, path: &str) -> Result<Self> {
+        let mut bytes = Vec::new();
+        vfs.read_bytes(path, &mut bytes)?;
+        let mut reader = bytes.as_slice();
+
+        let header: ReplayHeader = bincode::deserialize_from(&mut reader).map_err(|e| RockError::Asset(format!("Invalid replay `{}`: {}", path, e)))?;
+        if header.version != REPLAY_VERSION {
+            return Err(RockError::Asset(format!("Replay `{}` was recorded by an incompatible version of the engine", path)));
+        }
+        let mut frames = VecDeque::new();
+        while !reader.is_empty() {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push_back(frame),
+                //The game crashed while the last frame was being written, which is fine to leave out
+                Err(e) => {
+                    warn!("Replay `{}` ends in a broken frame, skipping it: {}", path, e);
+                    break;
+                },
+            }
+        }
+        info!("Loaded replay `{}` ({} frames)", path, frames.len());
+        Ok(Self {
+            seed: header.seed,
+            frames: frames,
+        })
+    }
+
+    pub fn next_frame(&mut self) -> Option<ReplayFrame> {
+        self.frames.pop_front()
+    }
+
+    pub fn frames_left(&self) -> usize {
+        self.frames.len()
+    }
+}
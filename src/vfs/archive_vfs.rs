@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, SeekFrom};
+use std::io::prelude::*;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use serde::{Serialize, Deserialize};
+
+use super::{VirtualFileSystem, GenericFile, FileMetadata, VfsPath};
+
+const MAGIC: &[u8; 4] = b"ROCK";
+const VERSION: u32 = 1;
+/// Magic, version and index length
+const HEADER_LEN: u64 = 4 + 4 + 8;
+
+/// Location of a single file inside the archive's data section.
+#[derive(Clone, Serialize, Deserialize)]
+struct ArchiveEntry {
+    path: String,
+    offset: u64,
+    size: u64,
+}
+
+/// A read-only VFS backed by a single `.rock` package,
+/// similar to Love2D's `.love` files.
+///
+/// The package layout is:
+/// - 4 bytes magic (`ROCK`)
+/// - `u32` format version (little endian)
+/// - `u64` index length in bytes (little endian)
+/// - The index, a `bincode` serialized list of entries
+/// - The data section, with all files stored back to back
+///
+/// The index is read once when the package is opened,
+/// files are read from the package on demand.
+pub struct ArchiveVFS {
+    file: Mutex<File>,
+    data_start: u64,
+    entries: HashMap<String, ArchiveEntry>,
+    modified: Option<SystemTime>, //Files inside the package share its modification time
+}
+
+impl ArchiveVFS {
+    /// Opens a package and reads its index.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let modified = metadata.modified().ok();
+        let file_len = metadata.len();
+        let corrupt = || Error::new(ErrorKind::InvalidData, format!("`{}` is a corrupt Rock package!", path));
+
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, format!("`{}` is not a Rock package!", path)));
+        }
+
+        let mut version = [0u8; 4];
+        file.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Unsupported Rock package version {}!", version)));
+        }
+
+        let mut index_len = [0u8; 8];
+        file.read_exact(&mut index_len)?;
+        let index_len = u64::from_le_bytes(index_len);
+
+        //Sizes come from the file itself, so they're checked before anything gets allocated with them
+        let data_start = HEADER_LEN.checked_add(index_len).filter(|start| *start <= file_len).ok_or_else(corrupt)?;
+        let mut index_bytes = vec![0u8; index_len as usize];
+        file.read_exact(&mut index_bytes)?;
+        let index: Vec<ArchiveEntry> = bincode::deserialize(&index_bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        let data_len = file_len - data_start;
+        for entry in &index {
+            let end = entry.offset.checked_add(entry.size).ok_or_else(corrupt)?;
+            if end > data_len {
+                return Err(corrupt());
+            }
+        }
+        let entries = index.into_iter().map(|entry| (entry.path.clone(), entry)).collect();
+
+        Ok(Self {
+            file: Mutex::new(file),
+            data_start: data_start,
+            entries: entries,
+            modified: modified,
+        })
+    }
+
+    /// Packs every file in `src_dir` (recursively) into a package at `out_path`.
+    /// `out_path` may be inside `src_dir`, an older package there doesn't get packed.
+    pub fn build(src_dir: &str, out_path: &str) -> Result<(), Error> {
+        let out_file = fs::canonicalize(out_path).ok();
+        let mut files = Vec::new();
+        collect_files(Path::new(src_dir), "", out_file.as_deref(), &mut files)?;
+        files.sort();
+
+        let mut index = Vec::new();
+        let mut offset = 0;
+        for (path, disk_path) in &files {
+            let size = fs::metadata(disk_path)?.len();
+            index.push(ArchiveEntry {
+                path: path.clone(),
+                offset: offset,
+                size: size,
+            });
+            offset += size;
+        }
+        let index_bytes = bincode::serialize(&index).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        let mut out = File::create(out_path)?;
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(index_bytes.len() as u64).to_le_bytes())?;
+        out.write_all(&index_bytes)?;
+        for (_, disk_path) in &files {
+            let mut file = File::open(disk_path)?;
+            std::io::copy(&mut file, &mut out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recursively collects `(package path, disk path)` pairs for all files in `dir`, except `skip`.
+fn collect_files(dir: &Path, prefix: &str, skip: Option<&Path>, files: &mut Vec<(String, std::path::PathBuf)>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &path, skip, files)?;
+        } else if skip.map_or(true, |skip| fs::canonicalize(entry.path()).ok().as_deref() != Some(skip)) {
+            files.push((path, entry.path()));
+        }
+    }
+    Ok(())
+}
+
+impl VirtualFileSystem for ArchiveVFS {
+    fn read_file(&self, path: &str) -> Result<GenericFile, Error> {
+        let mut bytes = Vec::new();
+        self.read_bytes(path, &mut bytes)?;
+        Ok(GenericFile::from_path(path, bytes))
+    }
+
+    fn write_file(&self, _path: &str, _data: GenericFile) -> Result<(), Error> {
+        Err(Error::new(ErrorKind::PermissionDenied, "Rock packages are read-only!"))
+    }
+
+    fn read_bytes(&self, path: &str, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        let path = VfsPath::new(path)?;
+        let path = path.as_str();
+        let entry = self.entries.get(path).ok_or_else(|| Error::new(ErrorKind::NotFound, format!("`{}` not found in package!", path)))?;
+        let mut file = self.file.lock().map_err(|_| Error::new(ErrorKind::Other, "Package file lock was poisoned!"))?;
+        file.seek(SeekFrom::Start(self.data_start + entry.offset))?;
+        let start = bytes.len();
+        bytes.resize(start + entry.size as usize, 0);
+        file.read_exact(&mut bytes[start..])?;
+        Ok(())
+    }
+
+    fn write_bytes(&self, _path: &str, _data: &[u8]) -> Result<(), Error> {
+        Err(Error::new(ErrorKind::PermissionDenied, "Rock packages are read-only!"))
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata, Error> {
+        let path = VfsPath::new(path)?;
+        let path = path.as_str();
+        if let Some(entry) = self.entries.get(path) {
+            return Ok(FileMetadata {
+                size: entry.size,
+                modified: self.modified,
+                is_dir: false,
+            });
+        }
+        //Directories aren't stored, they only exist as prefixes of file paths
+        let prefix = format!("{}/", path);
+        if path.is_empty() || self.entries.keys().any(|p| p.starts_with(&prefix)) {
+            return Ok(FileMetadata {
+                size: 0,
+                modified: self.modified,
+                is_dir: true,
+            });
+        }
+        Err(Error::new(ErrorKind::NotFound, format!("`{}` not found in package!", path)))
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, Error> {
+        let path = VfsPath::new(path)?;
+        let path = path.as_str();
+        let prefix = if path.is_empty() { String::new() } else { format!("{}/", path) };
+        let mut items: Vec<String> = self.entries.keys()
+            .filter(|p| p.starts_with(&prefix))
+            .filter_map(|p| p[prefix.len()..].split('/').next())
+            .map(|name| name.to_owned())
+            .collect();
+        if items.is_empty() && !path.is_empty() {
+            return Err(Error::new(ErrorKind::NotFound, format!("Directory `{}` not found in package!", path)));
+        }
+        items.sort();
+        items.dedup();
+        Ok(items)
+    }
+
+    fn create_dir(&self, _path: &str) -> Result<(), Error> {
+        Err(Error::new(ErrorKind::PermissionDenied, "Rock packages are read-only!"))
+    }
+
+    fn remove(&self, _path: &str) -> Result<(), Error> {
+        Err(Error::new(ErrorKind::PermissionDenied, "Rock packages are read-only!"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for a test to work in.
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("rock_archive_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn build_package(dir: &Path) -> String {
+        let src = dir.join("game");
+        fs::create_dir_all(src.join("assets/models")).unwrap();
+        fs::write(src.join("main.lua"), "print(\"hi\")").unwrap();
+        fs::write(src.join("assets/models/cube.gltf"), [1u8, 2, 3, 4]).unwrap();
+        fs::write(src.join("assets/empty.txt"), "").unwrap();
+
+        let out = dir.join("game.rock").to_string_lossy().into_owned();
+        ArchiveVFS::build(&src.to_string_lossy(), &out).unwrap();
+        out
+    }
+
+    #[test]
+    fn reads_back_built_package() {
+        let dir = test_dir("roundtrip");
+        let vfs = ArchiveVFS::open(&build_package(&dir)).unwrap();
+
+        let mut bytes = Vec::new();
+        vfs.read_bytes("main.lua", &mut bytes).unwrap();
+        assert_eq!(bytes, b"print(\"hi\")");
+        bytes.clear();
+        vfs.read_bytes("/assets\\models/cube.gltf", &mut bytes).unwrap();
+        assert_eq!(bytes, [1, 2, 3, 4]);
+        bytes.clear();
+        vfs.read_bytes("assets/empty.txt", &mut bytes).unwrap();
+        assert!(bytes.is_empty());
+
+        assert_eq!(vfs.list_dir("/").unwrap(), vec!["assets", "main.lua"]);
+        assert_eq!(vfs.list_dir("assets").unwrap(), vec!["empty.txt", "models"]);
+        assert!(vfs.metadata("assets/models").unwrap().is_dir);
+        assert_eq!(vfs.metadata("assets/models/cube.gltf").unwrap().size, 4);
+        assert_eq!(vfs.read_bytes("missing.lua", &mut bytes).unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(vfs.write_bytes("main.lua", b"").unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn repacking_into_the_source_dir_skips_the_old_package() {
+        let dir = test_dir("repack");
+        let src = dir.join("game");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("main.lua"), "print(\"hi\")").unwrap();
+        let out = src.join("game.rock").to_string_lossy().into_owned();
+
+        ArchiveVFS::build(&src.to_string_lossy(), &out).unwrap();
+        ArchiveVFS::build(&src.to_string_lossy(), &out).unwrap();
+        let vfs = ArchiveVFS::open(&out).unwrap();
+        assert_eq!(vfs.list_dir("/").unwrap(), vec!["main.lua"]);
+        let mut bytes = Vec::new();
+        vfs.read_bytes("main.lua", &mut bytes).unwrap();
+        assert_eq!(bytes, b"print(\"hi\")");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_truncated_package() {
+        let dir = test_dir("truncated");
+        let path = build_package(&dir);
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 2]).unwrap();
+        assert_eq!(ArchiveVFS::open(&path).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_huge_index_length() {
+        let dir = test_dir("index_len");
+        let path = dir.join("bad.rock");
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+        assert_eq!(ArchiveVFS::open(&path.to_string_lossy()).err().unwrap().kind(), ErrorKind::InvalidData);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
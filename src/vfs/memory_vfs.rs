@@ -0,0 +1,201 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, ErrorKind};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use super::{VirtualFileSystem, GenericFile, FileMetadata, VfsPath};
+
+struct MemoryFile {
+    data: Vec<u8>,
+    modified: SystemTime,
+}
+
+/// A VFS that keeps all files in memory.
+/// Useful for procedurally generated assets, and for
+/// loading assets without touching the disk.
+/// Directories are created implicitly when writing files.
+pub struct MemoryVFS {
+    files: RwLock<HashMap<String, MemoryFile>>,
+    dirs: RwLock<HashSet<String>>, //Directories created with `create_dir`
+}
+
+impl MemoryVFS {
+    pub fn new() -> Self {
+        Self {
+            files: RwLock::new(HashMap::new()),
+            dirs: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// Returns true if any file or directory lives inside `path`.
+    fn has_children(&self, path: &str) -> Result<bool, Error> {
+        if path.is_empty() {
+            return Ok(true);
+        }
+        let prefix = format!("{}/", path);
+        let files = self.files.read().map_err(|_| poisoned())?;
+        let dirs = self.dirs.read().map_err(|_| poisoned())?;
+        Ok(files.keys().chain(dirs.iter()).any(|p| p.starts_with(&prefix)))
+    }
+
+    fn is_dir(&self, path: &str) -> Result<bool, Error> {
+        Ok(self.dirs.read().map_err(|_| poisoned())?.contains(path) || self.has_children(path)?)
+    }
+}
+
+fn poisoned() -> Error {
+    Error::new(ErrorKind::Other, "MemoryVFS lock was poisoned!")
+}
+
+fn not_found(path: &str) -> Error {
+    Error::new(ErrorKind::NotFound, format!("`{}` not found in memory!", path))
+}
+
+impl VirtualFileSystem for MemoryVFS {
+    fn read_file(&self, path: &str) -> Result<GenericFile, Error> {
+        let mut bytes = Vec::new();
+        self.read_bytes(path, &mut bytes)?;
+        Ok(GenericFile::from_path(path, bytes))
+    }
+
+    fn write_file(&self, path: &str, data: GenericFile) -> Result<(), Error> {
+        self.write_bytes(path, &data.data)
+    }
+
+    fn read_bytes(&self, path: &str, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        let path = VfsPath::new(path)?;
+        let files = self.files.read().map_err(|_| poisoned())?;
+        let file = files.get(path.as_str()).ok_or_else(|| not_found(path.as_str()))?;
+        bytes.extend_from_slice(&file.data);
+        Ok(())
+    }
+
+    fn write_bytes(&self, path: &str, data: &[u8]) -> Result<(), Error> {
+        let path = VfsPath::new(path)?;
+        if path.is_root() || self.is_dir(path.as_str())? {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("`{}` is a directory!", path)));
+        }
+        let mut files = self.files.write().map_err(|_| poisoned())?;
+        files.insert(path.as_str().to_owned(), MemoryFile {
+            data: data.to_vec(),
+            modified: SystemTime::now(),
+        });
+        Ok(())
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata, Error> {
+        let path = VfsPath::new(path)?;
+        if let Some(file) = self.files.read().map_err(|_| poisoned())?.get(path.as_str()) {
+            return Ok(FileMetadata {
+                size: file.data.len() as u64,
+                modified: Some(file.modified),
+                is_dir: false,
+            });
+        }
+        if self.is_dir(path.as_str())? {
+            return Ok(FileMetadata {
+                size: 0,
+                modified: None,
+                is_dir: true,
+            });
+        }
+        Err(not_found(path.as_str()))
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, Error> {
+        let path = VfsPath::new(path)?;
+        if !self.is_dir(path.as_str())? {
+            return Err(not_found(path.as_str()));
+        }
+        let prefix = if path.is_root() { String::new() } else { format!("{}/", path.as_str()) };
+        let files = self.files.read().map_err(|_| poisoned())?;
+        let dirs = self.dirs.read().map_err(|_| poisoned())?;
+        let mut items: Vec<String> = files.keys().chain(dirs.iter())
+            .filter(|p| p.starts_with(&prefix))
+            .filter_map(|p| p[prefix.len()..].split('/').next())
+            .map(|name| name.to_owned())
+            .collect();
+        items.sort();
+        items.dedup();
+        Ok(items)
+    }
+
+    fn create_dir(&self, path: &str) -> Result<(), Error> {
+        let path = VfsPath::new(path)?;
+        if self.files.read().map_err(|_| poisoned())?.contains_key(path.as_str()) {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("`{}` is a file!", path)));
+        }
+        if !path.is_root() {
+            self.dirs.write().map_err(|_| poisoned())?.insert(path.as_str().to_owned());
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> Result<(), Error> {
+        let path = VfsPath::new(path)?;
+        if self.files.write().map_err(|_| poisoned())?.remove(path.as_str()).is_some() {
+            return Ok(());
+        }
+        if self.has_children(path.as_str())? {
+            return Err(Error::new(ErrorKind::Other, format!("Directory `{}` is not empty!", path)));
+        }
+        if self.dirs.write().map_err(|_| poisoned())?.remove(path.as_str()) {
+            return Ok(());
+        }
+        Err(not_found(path.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(vfs: &dyn VirtualFileSystem, path: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        vfs.read_bytes(path, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trips_files() {
+        let vfs = MemoryVFS::new();
+        vfs.write_bytes("saves/slot1.bin", &[1, 2, 3]).unwrap();
+        assert_eq!(read(&vfs, "saves/slot1.bin"), [1, 2, 3]);
+        assert_eq!(read(&vfs, "/saves\\slot1.bin"), [1, 2, 3]);
+
+        vfs.append_bytes("saves/slot1.bin", &[4]).unwrap();
+        assert_eq!(read(&vfs, "saves/slot1.bin"), [1, 2, 3, 4]);
+
+        vfs.write_file("notes.txt", GenericFile::from_path("notes.txt", b"hello".to_vec())).unwrap();
+        let file = vfs.read_file("notes.txt").unwrap();
+        assert_eq!(file.name, "notes");
+        assert_eq!(file.extension, "txt");
+        assert_eq!(file.data, b"hello");
+    }
+
+    #[test]
+    fn tracks_directories() {
+        let vfs = MemoryVFS::new();
+        vfs.write_bytes("a/b/c.txt", b"c").unwrap();
+        vfs.create_dir("empty").unwrap();
+
+        assert!(vfs.metadata("a/b").unwrap().is_dir);
+        assert_eq!(vfs.metadata("a/b/c.txt").unwrap().size, 1);
+        assert_eq!(vfs.list_dir("/").unwrap(), vec!["a", "empty"]);
+        assert_eq!(vfs.list_dir("a").unwrap(), vec!["b"]);
+        assert_eq!(vfs.write_bytes("a", b"").unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        assert_eq!(vfs.remove("a/b").unwrap_err().kind(), ErrorKind::Other);
+        vfs.remove("a/b/c.txt").unwrap();
+        vfs.remove("empty").unwrap();
+        assert!(!vfs.exists("a/b/c.txt"));
+        assert!(!vfs.exists("empty"));
+        assert_eq!(vfs.read_bytes("a/b/c.txt", &mut Vec::new()).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn rejects_escaping_paths() {
+        let vfs = MemoryVFS::new();
+        assert_eq!(vfs.write_bytes("../outside.txt", b"").unwrap_err().kind(), ErrorKind::PermissionDenied);
+    }
+}
@@ -1,9 +1,21 @@
 use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+mod path;
+pub use path::VfsPath;
 
 pub mod naive_vfs;
+pub mod archive_vfs;
+pub mod mount_vfs;
+pub mod memory_vfs;
+pub mod watcher;
 
 /// Generic file, to be used to store information
 /// and data of a file, so it can be used later.
+#[derive(Serialize, Deserialize)]
 pub struct GenericFile {
     pub name: String,
     pub extension: String,
@@ -11,10 +23,142 @@ pub struct GenericFile {
     pub data: Vec<u8>,
 }
 
+impl GenericFile {
+    /// Creates a file, taking its name and extension from `path`.
+    /// `name` does not include the extension.
+    pub fn from_path(path: &str, data: Vec<u8>) -> Self {
+        let p = Path::new(path);
+        let to_string = |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        Self {
+            name: to_string(p.file_stem()),
+            extension: to_string(p.extension()),
+            data: data,
+        }
+    }
+}
+
+/// Information about a file or directory in a VFS.
+#[derive(Clone, Debug)]
+pub struct FileMetadata {
+    pub size: u64, //In bytes, 0 for directories
+    pub modified: Option<SystemTime>, //None if the backend doesn't track it
+    pub is_dir: bool,
+}
+
 pub trait VirtualFileSystem {
     fn read_file(&self, path: &str) -> Result<GenericFile, Error>;
     fn write_file(&self, path: &str, data: GenericFile) -> Result<(), Error>;
 
     fn read_bytes(&self, path: &str, bytes: &mut Vec<u8>) -> Result<(), Error>;
     fn write_bytes(&self, path: &str, data: &[u8]) -> Result<(), Error>;
+
+    fn exists(&self, path: &str) -> bool {
+        self.metadata(path).is_ok()
+    }
+    fn metadata(&self, path: &str) -> Result<FileMetadata, Error>;
+    /// Returns the names of all items in a directory, sorted.
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, Error>;
+    fn create_dir(&self, path: &str) -> Result<(), Error>;
+    /// Removes a file or an empty directory.
+    fn remove(&self, path: &str) -> Result<(), Error>;
+
+    fn append_bytes(&self, path: &str, data: &[u8]) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        match self.read_bytes(path, &mut bytes) {
+            Ok(()) => {},
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => bytes.clear(),
+            Err(e) => return Err(e),
+        }
+        bytes.extend_from_slice(data);
+        self.write_bytes(path, &bytes)
+    }
+
+    /// Returns the directory on disk writes end up in, if there is one.
+    fn save_dir(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Reads a file from any VFS and deserializes it into `T` using `bincode`,
+/// for example to cache preprocessed assets.
+pub fn read_typed<T: DeserializeOwned>(vfs: &dyn VirtualFileSystem, path: &str) -> Result<T, Error> {
+    let mut bytes = Vec::new();
+    vfs.read_bytes(path, &mut bytes)?;
+    bincode::deserialize(&bytes).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, format!("Invalid data in `{}`: {}", path, e)))
+}
+
+/// Serializes `data` using `bincode` and writes it to a file in any VFS.
+pub fn write_typed<T: Serialize>(vfs: &dyn VirtualFileSystem, path: &str, data: &T) -> Result<(), Error> {
+    let bytes = bincode::serialize(data).map_err(|e| Error::new(std::io::ErrorKind::InvalidData, e))?;
+    vfs.write_bytes(path, &bytes)
+}
+
+/// Checks that a game's identity is a single plain directory name,
+/// so its save directory can't end up anywhere else on disk.
+pub fn check_identity(identity: &str) -> crate::error::Result<()> {
+    let valid = !identity.is_empty()
+        && identity != "."
+        && !identity.contains("..")
+        && !identity.contains(|c| matches!(c, '/' | '\\' | ':' | '\0'));
+    if valid {
+        Ok(())
+    } else {
+        Err(crate::error::RockError::Vfs(Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid identity `{}`, it must be a plain directory name", identity.escape_debug()))))
+    }
+}
+
+/// Returns the directory a game with the given identity may write to,
+/// similar to Love2D's save directory.
+/// - Windows: `%APPDATA%/Rock/<identity>`
+/// - macOS: `~/Library/Application Support/Rock/<identity>`
+/// - Other: `$XDG_DATA_HOME/rock/<identity>` (or `~/.local/share/rock/<identity>`)
+///
+/// The identity should have passed `check_identity`.
+pub fn save_directory(identity: &str) -> PathBuf {
+    let env_path = |var: &str| std::env::var_os(var).map(PathBuf::from);
+    let home = env_path("HOME").unwrap_or_else(|| PathBuf::from("."));
+
+    let base = if cfg!(target_os = "windows") {
+        env_path("APPDATA").unwrap_or(home).join("Rock")
+    } else if cfg!(target_os = "macos") {
+        home.join("Library").join("Application Support").join("Rock")
+    } else {
+        env_path("XDG_DATA_HOME").unwrap_or_else(|| home.join(".local").join("share")).join("rock")
+    };
+    base.join(identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use memory_vfs::MemoryVFS;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Scene {
+        name: String,
+        positions: Vec<[f32; 3]>,
+        parent: Option<u32>,
+    }
+
+    #[test]
+    fn typed_round_trip() {
+        let vfs = MemoryVFS::new();
+        let scene = Scene {
+            name: "level1".to_owned(),
+            positions: vec![[0.0, 1.0, 2.0], [-3.5, 0.25, 8.0]],
+            parent: Some(7),
+        };
+        write_typed(&vfs, "cache/level1.bin", &scene).unwrap();
+        assert!(vfs.exists("cache/level1.bin"));
+        assert_eq!(vfs.list_dir("cache").unwrap(), vec!["level1.bin"]);
+        assert_eq!(read_typed::<Scene>(&vfs, "cache/level1.bin").unwrap(), scene);
+    }
+
+    #[test]
+    fn typed_read_rejects_invalid_data() {
+        let vfs = MemoryVFS::new();
+        vfs.write_bytes("broken.bin", &[1]).unwrap();
+        assert_eq!(read_typed::<Scene>(&vfs, "broken.bin").unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(read_typed::<Scene>(&vfs, "missing.bin").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
 }
@@ -0,0 +1,275 @@
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+use super::{VirtualFileSystem, GenericFile, FileMetadata, VfsPath};
+
+/// Priority of the game's own files (source directory or package).
+pub const PRIORITY_GAME: i32 = 0;
+/// Priority of mods and patches, which override the game's files.
+pub const PRIORITY_MOD: i32 = 50;
+/// Priority of the save directory, which overrides everything else.
+pub const PRIORITY_SAVE: i32 = 100;
+
+struct Mount {
+    point: String, //Normalized through `VfsPath`
+    priority: i32,
+    vfs: Box<dyn VirtualFileSystem>,
+}
+
+impl Mount {
+    /// Returns `path` relative to this mount, if the mount contains it.
+    fn relative<'a>(&self, path: &'a str) -> Option<&'a str> {
+        if self.point.is_empty() {
+            return Some(path);
+        }
+        if path == self.point {
+            return Some("");
+        }
+        if path.starts_with(&self.point) && path[self.point.len()..].starts_with('/') {
+            return Some(&path[self.point.len() + 1..]);
+        }
+        None
+    }
+
+    /// Returns the item directly inside `path` that leads to this mount point,
+    /// if the mount point is somewhere below `path`.
+    fn child_of<'a>(&'a self, path: &str) -> Option<&'a str> {
+        let rest = if path.is_empty() {
+            self.point.as_str()
+        } else if self.point.starts_with(path) && self.point[path.len()..].starts_with('/') {
+            &self.point[path.len() + 1..]
+        } else {
+            return None;
+        };
+        rest.split('/').next().filter(|name| !name.is_empty())
+    }
+}
+
+/// A VFS that layers several other VFS backends under mount points.
+/// When multiple mounts contain a path, the one with the highest
+/// priority wins, so mods and patches can override the base game
+/// without modifying it. For equal priorities, the most recent mount wins.
+///
+/// Reads go to the first mount that has the file,
+/// writes go to the first mount that accepts them.
+pub struct MountVFS {
+    mounts: Vec<Mount>, //Sorted from highest to lowest priority
+}
+
+impl MountVFS {
+    pub fn new() -> Self {
+        Self {
+            mounts: Vec::new(),
+        }
+    }
+
+    /// Mounts `vfs` at `point`. An empty point (or `/`) mounts it at the root.
+    pub fn mount(&mut self, point: &str, priority: i32, vfs: Box<dyn VirtualFileSystem>) -> Result<(), Error> {
+        let mount = Mount {
+            point: VfsPath::new(point)?.as_str().to_owned(),
+            priority: priority,
+            vfs: vfs,
+        };
+        let index = self.mounts.iter().position(|m| m.priority <= priority).unwrap_or(self.mounts.len());
+        self.mounts.insert(index, mount);
+        Ok(())
+    }
+
+    /// Removes the highest priority mount at `point`, returning it.
+    pub fn unmount(&mut self, point: &str) -> Option<Box<dyn VirtualFileSystem>> {
+        let point = VfsPath::new(point).ok()?;
+        let index = self.mounts.iter().position(|m| m.point == point.as_str())?;
+        Some(self.mounts.remove(index).vfs)
+    }
+
+    /// Calls `func` on every mount containing `path`, in priority order,
+    /// until it returns something other than an error of kind `skip`.
+    fn first<T, F>(&self, path: &str, skip: ErrorKind, mut func: F) -> Result<T, Error>
+    where
+        F: FnMut(&dyn VirtualFileSystem, &str) -> Result<T, Error>
+    {
+        let path = VfsPath::new(path)?;
+        let path = path.as_str();
+        for mount in &self.mounts {
+            if let Some(rel) = mount.relative(path) {
+                match func(mount.vfs.as_ref(), rel) {
+                    Err(e) if e.kind() == skip => continue,
+                    result => return result,
+                }
+            }
+        }
+        Err(Error::new(skip, format!("No mount could handle `{}`!", path)))
+    }
+}
+
+impl VirtualFileSystem for MountVFS {
+    fn read_file(&self, path: &str) -> Result<GenericFile, Error> {
+        self.first(path, ErrorKind::NotFound, |vfs, rel| vfs.read_file(rel))
+    }
+
+    fn write_file(&self, path: &str, data: GenericFile) -> Result<(), Error> {
+        self.write_bytes(path, &data.data)
+    }
+
+    fn read_bytes(&self, path: &str, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        self.first(path, ErrorKind::NotFound, |vfs, rel| vfs.read_bytes(rel, bytes))
+    }
+
+    fn write_bytes(&self, path: &str, data: &[u8]) -> Result<(), Error> {
+        self.first(path, ErrorKind::PermissionDenied, |vfs, rel| vfs.write_bytes(rel, data))
+    }
+
+    /// Directories leading up to a mount point exist, even when no mount has them.
+    fn metadata(&self, path: &str) -> Result<FileMetadata, Error> {
+        match self.first(path, ErrorKind::NotFound, |vfs, rel| vfs.metadata(rel)) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let path = VfsPath::new(path)?;
+                if !self.mounts.iter().any(|m| m.child_of(path.as_str()).is_some()) {
+                    return Err(e);
+                }
+                Ok(FileMetadata {
+                    size: 0,
+                    modified: None,
+                    is_dir: true,
+                })
+            },
+            result => result,
+        }
+    }
+
+    /// Lists the items of a directory across all mounts,
+    /// including the mount points inside it (or the directories leading up to them).
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, Error> {
+        let path = VfsPath::new(path)?;
+        let path = path.as_str();
+        let mut items = Vec::new();
+        let mut found = false;
+        for mount in &self.mounts {
+            if let Some(rel) = mount.relative(path) {
+                match mount.vfs.list_dir(rel) {
+                    Ok(mut list) => {
+                        found = true;
+                        items.append(&mut list);
+                    },
+                    Err(e) if e.kind() == ErrorKind::NotFound => {},
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if let Some(name) = mount.child_of(path) {
+                found = true;
+                items.push(name.to_owned());
+            }
+        }
+        if !found {
+            return Err(Error::new(ErrorKind::NotFound, format!("Directory `{}` not found!", path)));
+        }
+        items.sort();
+        items.dedup();
+        Ok(items)
+    }
+
+    fn create_dir(&self, path: &str) -> Result<(), Error> {
+        self.first(path, ErrorKind::PermissionDenied, |vfs, rel| vfs.create_dir(rel))
+    }
+
+    fn remove(&self, path: &str) -> Result<(), Error> {
+        self.first(path, ErrorKind::PermissionDenied, |vfs, rel| vfs.remove(rel))
+    }
+
+    fn append_bytes(&self, path: &str, data: &[u8]) -> Result<(), Error> {
+        self.first(path, ErrorKind::PermissionDenied, |vfs, rel| vfs.append_bytes(rel, data))
+    }
+
+    /// Returns the save directory of the highest priority mount that has one.
+    fn save_dir(&self) -> Option<PathBuf> {
+        self.mounts.iter().find_map(|m| m.vfs.save_dir())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::memory_vfs::MemoryVFS;
+
+    fn layer(files: &[(&str, &str)]) -> Box<dyn VirtualFileSystem> {
+        let vfs = MemoryVFS::new();
+        for (path, contents) in files {
+            vfs.write_bytes(path, contents.as_bytes()).unwrap();
+        }
+        Box::new(vfs)
+    }
+
+    fn read(vfs: &dyn VirtualFileSystem, path: &str) -> String {
+        let mut bytes = Vec::new();
+        vfs.read_bytes(path, &mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn higher_priority_shadows_lower() {
+        let mut vfs = MountVFS::new();
+        vfs.mount("/", PRIORITY_GAME, layer(&[("main.lua", "game"), ("conf.lua", "game conf")])).unwrap();
+        vfs.mount("/", PRIORITY_MOD, layer(&[("main.lua", "mod")])).unwrap();
+
+        assert_eq!(read(&vfs, "main.lua"), "mod");
+        //Files the mod doesn't have fall through to the game
+        assert_eq!(read(&vfs, "conf.lua"), "game conf");
+        assert_eq!(vfs.list_dir("/").unwrap(), vec!["conf.lua", "main.lua"]);
+
+        vfs.unmount("/").unwrap();
+        assert_eq!(read(&vfs, "main.lua"), "game");
+    }
+
+    #[test]
+    fn mount_order_does_not_matter() {
+        let mut vfs = MountVFS::new();
+        vfs.mount("/", PRIORITY_MOD, layer(&[("main.lua", "mod")])).unwrap();
+        vfs.mount("/", PRIORITY_GAME, layer(&[("main.lua", "game")])).unwrap();
+        assert_eq!(read(&vfs, "main.lua"), "mod");
+    }
+
+    #[test]
+    fn writes_go_to_highest_priority() {
+        let mut vfs = MountVFS::new();
+        vfs.mount("/", PRIORITY_GAME, layer(&[("data.txt", "game")])).unwrap();
+        vfs.mount("/", PRIORITY_SAVE, layer(&[])).unwrap();
+
+        vfs.write_bytes("data.txt", b"saved").unwrap();
+        assert_eq!(read(&vfs, "data.txt"), "saved");
+        //The game's own copy is left alone
+        let save = vfs.unmount("/").unwrap();
+        assert_eq!(read(save.as_ref(), "data.txt"), "saved");
+        assert_eq!(read(&vfs, "data.txt"), "game");
+    }
+
+    #[test]
+    fn mounts_under_a_point() {
+        let mut vfs = MountVFS::new();
+        vfs.mount("/", PRIORITY_GAME, layer(&[("main.lua", "game")])).unwrap();
+        vfs.mount("mods/extra", PRIORITY_MOD, layer(&[("init.lua", "extra")])).unwrap();
+
+        assert_eq!(read(&vfs, "mods/extra/init.lua"), "extra");
+        assert_eq!(vfs.list_dir("mods").unwrap(), vec!["extra"]);
+        assert_eq!(vfs.read_bytes("init.lua", &mut Vec::new()).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn directories_above_mount_points_exist() {
+        let mut vfs = MountVFS::new();
+        vfs.mount("/", PRIORITY_GAME, layer(&[("main.lua", "game")])).unwrap();
+        vfs.mount("mods/extra/levels", PRIORITY_MOD, layer(&[("1.lua", "level")])).unwrap();
+
+        for dir in &["mods", "/mods", "mods/extra"] {
+            assert!(vfs.metadata(dir).unwrap().is_dir, "`{}` should be a directory", dir);
+        }
+        assert!(vfs.exists("mods/extra/levels/1.lua"));
+        assert!(!vfs.exists("mod"));
+        assert!(!vfs.exists("mods/other"));
+        assert_eq!(vfs.list_dir("").unwrap(), vec!["main.lua", "mods"]);
+        assert_eq!(vfs.list_dir("mods").unwrap(), vec!["extra"]);
+        assert_eq!(vfs.list_dir("mods/extra").unwrap(), vec!["levels"]);
+        assert_eq!(vfs.list_dir("mods/extra/levels").unwrap(), vec!["1.lua"]);
+        assert_eq!(vfs.list_dir("mod").unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
@@ -1,17 +1,58 @@
 use std::io::{Error, ErrorKind};
 use std::io::prelude::*;
-use std::fs::File;
-use std::path::Path;
+use std::fs::{self, File};
+use std::path::PathBuf;
 
-use super::{VirtualFileSystem, GenericFile};
+use super::{VirtualFileSystem, GenericFile, FileMetadata, VfsPath};
 
 /// A very basic VFS that does no compression
 /// and stores files directly on the disk.
-pub struct NaiveVFS;
+/// Reads first look in the save directory (if there is one),
+/// and fall back to the game's source directory.
+/// Writes are only allowed inside the save directory.
+pub struct NaiveVFS {
+    root: PathBuf,
+    save_dir: Option<PathBuf>,
+}
 
 impl NaiveVFS {
+    /// Creates a read-only VFS rooted at the working directory.
     pub fn new() -> Self {
-        Self {}
+        Self {
+            root: PathBuf::from("."),
+            save_dir: None,
+        }
+    }
+
+    /// Creates a VFS rooted at `root`, writing to `save_dir` if there is one.
+    pub fn from_dirs(root: PathBuf, save_dir: Option<PathBuf>) -> Self {
+        Self {
+            root: root,
+            save_dir: save_dir,
+        }
+    }
+
+    /// Directories to look in when reading, in order.
+    /// Files in the save directory take priority over the game's own files.
+    fn read_dirs(&self) -> impl Iterator<Item = &PathBuf> {
+        self.save_dir.iter().filter(move |dir| **dir != self.root).chain(std::iter::once(&self.root))
+    }
+
+    /// Finds the first directory containing `path`.
+    fn find(&self, path: &str) -> Result<PathBuf, Error> {
+        let path = VfsPath::new(path)?;
+        self.read_dirs()
+            .map(|dir| dir.join(path.as_str()))
+            .find(|p| p.exists())
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("`{}` not found!", path)))
+    }
+
+    fn save_path(&self, path: &str) -> Result<PathBuf, Error> {
+        let path = VfsPath::new(path)?;
+        match self.save_dir {
+            Some(ref dir) => Ok(dir.join(path.as_str())),
+            None => Err(Error::new(ErrorKind::PermissionDenied, "VFS has no save directory to write to!")),
+        }
     }
 }
 
@@ -19,25 +60,165 @@ impl VirtualFileSystem for NaiveVFS {
     fn read_file(&self, path: &str) -> std::result::Result<GenericFile, Error> {
         let mut bytes = Vec::new();
         self.read_bytes(path, &mut bytes)?;
-        Ok(GenericFile {
-            name: "Unknown".to_owned(),
-            extension: "Unknown".to_owned(),
-            data: bytes,
-        })
+        Ok(GenericFile::from_path(path, bytes))
     }
 
     fn write_file(&self, path: &str, data: GenericFile) -> std::result::Result<(), Error> {
-        unimplemented!();
+        self.write_bytes(path, &data.data)
     }
 
     fn read_bytes(&self, path: &str, bytes: &mut Vec<u8>) -> Result<(), Error> {
-        let p = Path::new(path);
+        let p = self.find(path)?;
         let mut file = File::open(&p)?;
         file.read_to_end(bytes)?;
         Ok(())
     }
 
     fn write_bytes(&self, path: &str, data: &[u8]) -> Result<(), Error> {
-        unimplemented!();
+        let p = self.save_path(path)?;
+        if let Some(parent) = p.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&p)?;
+        file.write_all(data)?;
+        Ok(())
+    }
+
+    //Appends in place, as the default reads and rewrites the whole file
+    fn append_bytes(&self, path: &str, data: &[u8]) -> Result<(), Error> {
+        let p = self.save_path(path)?;
+        if let Some(parent) = p.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&p)?;
+        file.write_all(data)?;
+        Ok(())
+    }
+
+    fn metadata(&self, path: &str) -> Result<FileMetadata, Error> {
+        let meta = fs::metadata(self.find(path)?)?;
+        Ok(FileMetadata {
+            size: if meta.is_dir() { 0 } else { meta.len() },
+            modified: meta.modified().ok(),
+            is_dir: meta.is_dir(),
+        })
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, Error> {
+        let path = VfsPath::new(path)?;
+        let mut items = Vec::new();
+        let mut found = false;
+        for dir in self.read_dirs() {
+            let entries = match fs::read_dir(dir.join(path.as_str())) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            found = true;
+            for entry in entries {
+                items.push(entry?.file_name().to_string_lossy().into_owned());
+            }
+        }
+        if !found {
+            return Err(Error::new(ErrorKind::NotFound, format!("Directory `{}` not found!", path)));
+        }
+        items.sort();
+        items.dedup();
+        Ok(items)
+    }
+
+    fn create_dir(&self, path: &str) -> Result<(), Error> {
+        fs::create_dir_all(self.save_path(path)?)
+    }
+
+    fn remove(&self, path: &str) -> Result<(), Error> {
+        let p = self.save_path(path)?;
+        if p.is_dir() {
+            fs::remove_dir(p)
+        } else {
+            fs::remove_file(p)
+        }
+    }
+
+    fn save_dir(&self) -> Option<PathBuf> {
+        self.save_dir.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for a test to work in, with a game directory and a save directory in it.
+    fn test_dirs(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("rock_naive_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let game = dir.join("game");
+        let save = dir.join("save");
+        fs::create_dir_all(&game).unwrap();
+        fs::create_dir_all(&save).unwrap();
+        fs::write(game.join("main.lua"), "game").unwrap();
+        (dir, game, save)
+    }
+
+    fn read(vfs: &NaiveVFS, path: &str) -> String {
+        let mut bytes = Vec::new();
+        vfs.read_bytes(path, &mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn writes_only_go_to_the_save_dir() {
+        let (dir, game, save) = test_dirs("writes");
+        let vfs = NaiveVFS::from_dirs(game.clone(), Some(save.clone()));
+
+        vfs.write_bytes("data/slot1.txt", b"saved").unwrap();
+        vfs.append_bytes("data/slot1.txt", b"!").unwrap();
+        assert_eq!(fs::read(save.join("data/slot1.txt")).unwrap(), b"saved!");
+        assert!(!game.join("data").exists());
+
+        //Overriding a game file leaves the original alone
+        assert_eq!(read(&vfs, "main.lua"), "game");
+        vfs.write_bytes("main.lua", b"patched").unwrap();
+        assert_eq!(read(&vfs, "main.lua"), "patched");
+        assert_eq!(fs::read(game.join("main.lua")).unwrap(), b"game");
+
+        vfs.remove("main.lua").unwrap();
+        assert_eq!(read(&vfs, "main.lua"), "game");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn game_dir_is_read_only() {
+        let (dir, game, _) = test_dirs("read_only");
+        let vfs = NaiveVFS::from_dirs(game.clone(), None);
+
+        assert_eq!(read(&vfs, "main.lua"), "game");
+        assert_eq!(vfs.write_bytes("main.lua", b"").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(vfs.append_bytes("main.lua", b"").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(vfs.create_dir("new").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(vfs.remove("main.lua").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(fs::read(game.join("main.lua")).unwrap(), b"game");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn paths_cant_leave_the_save_dir() {
+        let (dir, game, save) = test_dirs("escape");
+        let vfs = NaiveVFS::from_dirs(game, Some(save.clone()));
+
+        assert_eq!(vfs.write_bytes("../escaped.txt", b"").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(vfs.write_bytes("data/../../escaped.txt", b"").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(vfs.read_bytes("../game/main.lua", &mut Vec::new()).unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(vfs.write_bytes("C:/escaped.txt", b"").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(!dir.join("escaped.txt").exists());
+
+        //Absolute paths are relative to the save directory, not the root of the disk
+        vfs.write_bytes("/absolute.txt", b"inside").unwrap();
+        assert_eq!(fs::read(save.join("absolute.txt")).unwrap(), b"inside");
+
+        fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+/// A normalized path inside a VFS.
+///
+/// Separators are always `/`, and `.` and empty components are removed.
+/// Paths are relative to the root of the VFS, so a leading `/`
+/// refers to that root rather than the root of the disk.
+/// Paths that would escape the root through `..`, or that name
+/// a drive (`C:/...`), are rejected.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VfsPath {
+    path: String,
+}
+
+impl VfsPath {
+    pub fn new(path: &str) -> Result<Self, Error> {
+        if path.contains('\0') {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Path `{}` contains a null byte!", path.escape_default())));
+        }
+        if path.contains(':') {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Path `{}` may not contain `:`!", path)));
+        }
+
+        let mut parts = Vec::new();
+        for part in path.split(|c| c == '/' || c == '\\') {
+            match part {
+                "" | "." => {},
+                ".." => {
+                    if parts.pop().is_none() {
+                        return Err(Error::new(ErrorKind::PermissionDenied, format!("Path `{}` escapes the VFS root!", path)));
+                    }
+                },
+                part => parts.push(part),
+            }
+        }
+
+        Ok(Self {
+            path: parts.join("/"),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+}
+
+impl fmt::Display for VfsPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "/{}", self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalized(path: &str) -> String {
+        VfsPath::new(path).unwrap().as_str().to_owned()
+    }
+
+    #[test]
+    fn rejects_escaping_the_root() {
+        assert_eq!(VfsPath::new("..").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(VfsPath::new("../secret.txt").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(VfsPath::new("a/../../b").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        assert_eq!(VfsPath::new("/..\\..\\etc/passwd").unwrap_err().kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn absolute_paths_stay_inside_the_root() {
+        assert_eq!(normalized("/etc/passwd"), "etc/passwd");
+        assert_eq!(normalized("//save//file.txt"), "save/file.txt");
+        assert!(VfsPath::new("/").unwrap().is_root());
+    }
+
+    #[test]
+    fn rejects_drive_letters() {
+        assert_eq!(VfsPath::new("C:/Windows").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(VfsPath::new("c:\\Windows").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(VfsPath::new("D:").unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn normalizes_backslashes() {
+        assert_eq!(normalized("assets\\models\\cube.gltf"), "assets/models/cube.gltf");
+        assert_eq!(normalized("assets/models\\cube.gltf"), "assets/models/cube.gltf");
+    }
+
+    #[test]
+    fn rejects_null_bytes() {
+        assert_eq!(VfsPath::new("save\0.txt").unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn resolves_dots() {
+        assert_eq!(normalized("a/../b"), "b");
+        assert_eq!(normalized("./a/./b/"), "a/b");
+        assert_eq!(normalized("a/b/../../c"), "c");
+        assert!(VfsPath::new("a/..").unwrap().is_root());
+    }
+
+    #[test]
+    fn displays_with_leading_slash() {
+        assert_eq!(VfsPath::new("a\\b").unwrap().to_string(), "/a/b");
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::VirtualFileSystem;
+
+/// How often watched files are checked for changes by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// State of a watched file the last time it was polled.
+/// `None` if the file didn't exist.
+type FileState = Option<(Option<SystemTime>, u64)>;
+
+/// Polls files in a VFS for changes.
+/// A file counts as changed when its modification time or size
+/// changes, or when it gets created or removed.
+pub struct FileWatcher {
+    watched: HashMap<String, FileState>,
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            watched: HashMap::new(),
+            interval: interval,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn watch(&mut self, vfs: &dyn VirtualFileSystem, path: &str) {
+        let state = file_state(vfs, path);
+        self.watched.insert(path.to_owned(), state);
+    }
+
+    pub fn is_watched(&self, path: &str) -> bool {
+        self.watched.contains_key(path)
+    }
+
+    /// Returns all watched files that changed since the last poll.
+    /// Does nothing until the poll interval has passed.
+    pub fn poll(&mut self, vfs: &dyn VirtualFileSystem) -> Vec<String> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+        for (path, state) in self.watched.iter_mut() {
+            let new_state = file_state(vfs, path);
+            if new_state != *state {
+                *state = new_state;
+                changed.push(path.clone());
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
+
+fn file_state(vfs: &dyn VirtualFileSystem, path: &str) -> FileState {
+    vfs.metadata(path).ok().map(|meta| (meta.modified, meta.size))
+}
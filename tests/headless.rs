@@ -0,0 +1,244 @@
+use rock::{Rock, HeadlessOptions};
+use rock::vfs::VirtualFileSystem;
+use rock::vfs::memory_vfs::MemoryVFS;
+use rock::events::RockEvent;
+use rock::input::DEFAULT_DEADZONE;
+use sdl2::event::Event;
+use sdl2::controller::{Axis, Button};
+
+/// A headless engine running a game made of `files`, with its save directory in memory.
+fn headless_game(files: &[(&str, &str)]) -> Rock {
+    let game = MemoryVFS::new();
+    for (path, contents) in files {
+        game.write_bytes(path, contents.as_bytes()).unwrap();
+    }
+    Rock::builder()
+        .vfs(Box::new(game))
+        .save_vfs(Box::new(MemoryVFS::new()))
+        .headless(true)
+        .build()
+        .unwrap()
+}
+
+fn global<'lua, T: mlua::FromLua<'lua>>(rock: &'lua Rock, name: &str) -> T {
+    rock.lua.globals().get(name).unwrap()
+}
+
+fn assert_no_error(rock: &Rock) {
+    assert!(rock.error_screen.is_none(), "{}", rock.error_screen.as_deref().unwrap_or_default());
+}
+
+const FILESYSTEM_GAME: &str = r#"
+frames = 0
+function rock.load()
+    rock.filesystem.write("save.txt", "hello")
+    rock.filesystem.append("save.txt", " world")
+    saved = rock.filesystem.read("save.txt")
+    escaped = pcall(rock.filesystem.write, "../escaped.txt", "nope")
+end
+function rock.update(dt)
+    frames = frames + 1
+    last_dt = dt
+end
+"#;
+
+#[test]
+fn runs_lua_api_headlessly() {
+    let mut rock = headless_game(&[("main.lua", FILESYSTEM_GAME)]);
+    rock.load();
+    for _ in 0..3 {
+        assert!(!rock.step_frame(0.25).unwrap());
+    }
+    assert_no_error(&rock);
+
+    assert_eq!(global::<String>(&rock, "saved"), "hello world");
+    assert!(!global::<bool>(&rock, "escaped"));
+    assert_eq!(global::<u32>(&rock, "frames"), 3);
+    assert_eq!(global::<f32>(&rock, "last_dt"), 0.25);
+
+    let mut bytes = Vec::new();
+    rock.ctx.borrow().vfs.read_bytes("save.txt", &mut bytes).unwrap();
+    assert_eq!(bytes, b"hello world");
+}
+
+#[test]
+fn run_headless_counts_frames() {
+    let mut rock = headless_game(&[("main.lua", FILESYSTEM_GAME)]);
+    rock.run_headless(&HeadlessOptions { dt: 0.5, frames: 4 }).unwrap();
+    assert_eq!(global::<u32>(&rock, "frames"), 4);
+    assert_eq!(global::<f32>(&rock, "last_dt"), 0.5);
+}
+
+#[test]
+fn run_headless_reports_lua_errors() {
+    let mut rock = headless_game(&[("main.lua", "function rock.update(dt) error(\"boom\") end")]);
+    let message = rock.run_headless(&HeadlessOptions::default()).unwrap_err();
+    assert!(message.contains("boom"), "{}", message);
+    assert!(message.contains("main.lua:1:"), "{}", message);
+}
+
+const REQUIRE_GAME: &str = r#"
+local util = require("lib.util")
+local ui = require("ui")
+answer = util.answer + ui.offset
+cached = require("lib.util") == util
+_, missing = pcall(require, "missing")
+has_time = type(os.time) == "function" and type(os.clock) == "function" and type(os.date) == "function"
+sandboxed = os.execute == nil and os.exit == nil and os.getenv == nil and io == nil
+    and dofile == nil and loadfile == nil and package.loadlib == nil
+"#;
+
+#[test]
+fn require_loads_modules_through_the_vfs() {
+    let rock = headless_game(&[
+        ("main.lua", REQUIRE_GAME),
+        ("lib/util.lua", "return { answer = 40 }"),
+        ("ui/init.lua", "return { offset = 2 }"),
+    ]);
+    assert_no_error(&rock);
+    assert_eq!(global::<u32>(&rock, "answer"), 42);
+    assert!(global::<bool>(&rock, "cached"));
+    assert!(global::<String>(&rock, "missing").contains("no file 'missing.lua' in the game's files"));
+    assert!(global::<bool>(&rock, "has_time"));
+    assert!(global::<bool>(&rock, "sandboxed"));
+}
+
+const VECTOR_GAME: &str = r#"
+local v = rock.math.vec3(1, 2, 3)
+x = v.x
+v.x = 4
+assigned = v.x
+unchanged = rock.math.vec3(1, 2, 3).x
+length = #v
+concatenated = "a" .. v
+doubled = v * 2
+doubled_left = 2 * v
+local err
+mismatched, err = pcall(function() return v * rock.math.quat_identity() end)
+mismatch_error = tostring(err)
+"#;
+
+#[test]
+fn vectors_behave_like_lua_values() {
+    let rock = headless_game(&[("main.lua", VECTOR_GAME)]);
+    assert_no_error(&rock);
+    assert_eq!(global::<f32>(&rock, "x"), 1.0);
+    assert_eq!(global::<f32>(&rock, "assigned"), 4.0);
+    assert_eq!(global::<f32>(&rock, "unchanged"), 1.0);
+    assert_eq!(global::<u32>(&rock, "length"), 3);
+    assert_eq!(global::<String>(&rock, "concatenated"), "avec3(4, 2, 3)");
+
+    let doubled: rock::lua_api::lua_math::LuaVec3 = global(&rock, "doubled");
+    assert_eq!(*doubled.vec, glam::Vec3::new(8.0, 4.0, 6.0));
+    let doubled_left: rock::lua_api::lua_math::LuaVec3 = global(&rock, "doubled_left");
+    assert_eq!(*doubled_left.vec, *doubled.vec);
+
+    assert!(!global::<bool>(&rock, "mismatched"));
+    let mismatch_error = global::<String>(&rock, "mismatch_error");
+    assert!(mismatch_error.contains("userdata is not expected type"), "{}", mismatch_error);
+}
+
+const REPLAY_GAME: &str = r#"
+log = {}
+function rock.keypressed(key, scancode, isrepeat)
+    table.insert(log, "keypressed " .. key .. " " .. scancode .. " " .. tostring(isrepeat))
+end
+function rock.textinput(text)
+    table.insert(log, "textinput " .. text)
+end
+function rock.update(dt)
+    table.insert(log, "update " .. dt .. " " .. math.random(1000000))
+end
+"#;
+
+#[test]
+fn replays_recorded_sessions() {
+    let game = MemoryVFS::new();
+    game.write_bytes("main.lua", REPLAY_GAME.as_bytes()).unwrap();
+    let mut rock = Rock::builder()
+        .vfs(Box::new(game))
+        .save_vfs(Box::new(MemoryVFS::new()))
+        .headless(true)
+        .record("test.replay")
+        .build()
+        .unwrap();
+    rock.load();
+    rock.step_frame(0.1).unwrap();
+    rock.handle_event(&RockEvent::KeyPressed { key: "a".to_owned(), scancode: "a".to_owned(), repeat: false });
+    rock.handle_event(&RockEvent::TextInput { text: "a".to_owned() });
+    rock.step_frame(0.25).unwrap();
+    rock.step_frame(0.05).unwrap();
+    rock.handle_event(&RockEvent::KeyPressed { key: "space".to_owned(), scancode: "space".to_owned(), repeat: false });
+    rock.step_frame(0.5).unwrap();
+    assert_no_error(&rock);
+    let recorded: Vec<String> = global(&rock, "log");
+    assert_eq!(recorded.len(), 7);
+
+    let mut recording = Vec::new();
+    rock.ctx.borrow().vfs.read_bytes("test.replay", &mut recording).unwrap();
+    let game = MemoryVFS::new();
+    game.write_bytes("main.lua", REPLAY_GAME.as_bytes()).unwrap();
+    let save = MemoryVFS::new();
+    save.write_bytes("test.replay", &recording).unwrap();
+    let mut rock = Rock::builder()
+        .vfs(Box::new(game))
+        .save_vfs(Box::new(save))
+        .headless(true)
+        .replay("test.replay")
+        .build()
+        .unwrap();
+    assert!(rock.is_replaying());
+    rock.run_headless(&HeadlessOptions::default()).unwrap();
+    assert!(!rock.is_replaying());
+    let replayed: Vec<String> = global(&rock, "log");
+    assert_eq!(replayed, recorded);
+}
+
+const GAMEPAD_GAME: &str = r#"
+axis_events = 0
+function rock.gamepadpressed(id, button)
+    pressed = id .. " " .. button
+end
+function rock.gamepadaxis(id, axis, value)
+    axis_events = axis_events + 1
+    last_axis = id .. " " .. axis
+    last_value = value
+end
+"#;
+
+fn stick_event(value: i16) -> RockEvent {
+    let event = Event::ControllerAxisMotion { timestamp: 0, which: 1, axis: Axis::LeftX, value: value };
+    RockEvent::from_sdl(&event).unwrap()
+}
+
+#[test]
+fn gamepad_events_reach_the_game() {
+    let mut rock = headless_game(&[("main.lua", GAMEPAD_GAME)]);
+    let pressed = RockEvent::from_sdl(&Event::ControllerButtonDown { timestamp: 0, which: 1, button: Button::A }).unwrap();
+    assert!(!rock.handle_event(&pressed));
+    assert_eq!(global::<String>(&rock, "pressed"), "1 a");
+    assert!(rock.ctx.borrow().input.is_gamepad_down(1, "a"));
+
+    //Stick noise inside the deadzone never reaches the game
+    rock.handle_event(&stick_event(3000));
+    rock.handle_event(&stick_event(-3000));
+    assert_eq!(global::<u32>(&rock, "axis_events"), 0);
+    assert_eq!(rock.ctx.borrow().input.gamepad_axis(1, "leftx"), 0.0);
+
+    //Outside of it, what's left gets rescaled to the full range
+    rock.handle_event(&stick_event(i16::MAX / 2));
+    let expected = (0.5 - DEFAULT_DEADZONE) / (1.0 - DEFAULT_DEADZONE);
+    assert_eq!(global::<u32>(&rock, "axis_events"), 1);
+    assert_eq!(global::<String>(&rock, "last_axis"), "1 leftx");
+    assert!((global::<f32>(&rock, "last_value") - expected).abs() < 1e-4);
+    assert!((rock.ctx.borrow().input.gamepad_axis(1, "leftx") - expected).abs() < 1e-4);
+
+    rock.handle_event(&stick_event(i16::MIN));
+    assert_eq!(global::<f32>(&rock, "last_value"), -1.0);
+
+    //Letting go of the stick tells the game it's back at 0
+    rock.handle_event(&stick_event(1000));
+    assert_eq!(global::<u32>(&rock, "axis_events"), 3);
+    assert_eq!(global::<f32>(&rock, "last_value"), 0.0);
+    assert_no_error(&rock);
+}